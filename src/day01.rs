@@ -1,6 +1,8 @@
-use std::io::{self};
+use std::io::{self, Write};
 
-fn count_crossings(old: i32, new: i32) -> i32 {
+/// Number of times the dial passes position 0 strictly between `old` and `new`,
+/// both unwrapped (not reduced modulo `size`).
+fn count_crossings(old: i128, new: i128, size: i128) -> i128 {
     let a = old.min(new);
     let b = old.max(new);
 
@@ -11,43 +13,271 @@ fn count_crossings(old: i32, new: i32) -> i32 {
     let lower = a + 1;
     let upper = b - 1;
 
-    upper.div_euclid(100) - (lower - 1).div_euclid(100)
+    upper.div_euclid(size) - (lower - 1).div_euclid(size)
 }
 
-pub fn solve01(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut pos: i32 = 50;
-    let mut stops: i32 = 0;
-    let mut passes: i32 = 0;
+/// A circular dial with positions `0..size`, counting how often it stops on
+/// position 0 and how many clicks land on it. Arithmetic is done in `i128`, so any `i64` size and
+/// rotation is safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dial {
+    size: i64,
+    pos: i64,
+    stops: i128,
+    zero_clicks: i128,
+}
+
+impl Dial {
+    /// Dial with `size` positions (must be positive) starting at `start`,
+    /// which is wrapped into range.
+    pub fn new(size: i64, start: i64) -> Self {
+        assert!(size > 0, "dial size must be positive");
+        Dial {
+            size,
+            pos: start.rem_euclid(size),
+            stops: 0,
+            zero_clicks: 0,
+        }
+    }
+
+    pub fn position(&self) -> i64 {
+        self.pos
+    }
+
+    /// Turns the dial by `delta` clicks (negative is left) and returns how many
+    /// times it passed 0 before coming to rest.
+    pub fn rotate(&mut self, delta: i64) -> i128 {
+        let size = self.size as i128;
+        let old_pos = self.pos as i128;
+        let new_pos = old_pos + delta as i128;
+
+        let passes = count_crossings(old_pos, new_pos, size);
+        self.zero_clicks += passes;
+
+        self.pos = new_pos.rem_euclid(size) as i64;
+
+        if self.pos == 0 {
+            self.stops += 1;
+            // a zero-click rotation re-reports the stop without clicking onto 0
+            if delta != 0 {
+                self.zero_clicks += 1;
+            }
+        }
+        passes
+    }
+
+    /// Part 1: rotations that ended on 0.
+    pub fn part_1(&self) -> i128 {
+        self.stops
+    }
+
+    /// Part 2: every click that left the dial on 0, mid-rotation or at the end.
+    pub fn part_2(&self) -> i128 {
+        self.zero_clicks
+    }
+}
+
+impl Default for Dial {
+    /// The puzzle dial: 100 positions, starting at 50.
+    fn default() -> Self {
+        Dial::new(100, 50)
+    }
+}
+
+/// Parses an instruction like "L68" into a signed number of clicks.
+fn parse_instruction(line: &str) -> io::Result<i64> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "empty instruction",
+        ));
+    }
+
+    let (letter, number_str) = line.split_at(1);
+    let n: i64 = number_str
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    match letter {
+        "L" => Ok(-n),
+        "R" => Ok(n),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "bad instruction",
+        )),
+    }
+}
 
+/// Applies the same instruction stream to every dial in `dials`.
+pub fn run_dials(
+    lines: impl Iterator<Item = io::Result<String>>,
+    dials: &mut [Dial],
+) -> io::Result<()> {
     for line in lines {
         let line = line?; // <-- now type is correct!
-        let line = line.trim();
-
-        let (letter, number_str) = line.split_at(1);
-        let n: i32 = number_str
-            .parse()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-        let old_pos = pos;
-        let delta = match letter {
-            "L" => -n,
-            "R" => n,
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "bad instruction",
-                ))
-            }
-        };
+        let delta = parse_instruction(&line)?;
+        for dial in dials.iter_mut() {
+            dial.rotate(delta);
+        }
+    }
+    Ok(())
+}
+
+/// One instruction applied to a dial, as recorded by `trace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// 1-based line number of the instruction.
+    pub line: usize,
+    pub instruction: String,
+    pub old: i64,
+    pub new: i64,
+    /// Times 0 was passed during the rotation, not counting the final position.
+    pub passes: i128,
+    pub stopped: bool,
+}
+
+impl TraceStep {
+    pub fn touches_zero(&self) -> bool {
+        self.passes > 0 || self.stopped
+    }
+}
+
+/// Replays the instructions on `dial`, recording every step.
+pub fn trace(
+    lines: impl Iterator<Item = io::Result<String>>,
+    mut dial: Dial,
+) -> io::Result<Vec<TraceStep>> {
+    let mut steps = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        let delta = parse_instruction(&line)?;
+        let old = dial.position();
+        let passes = dial.rotate(delta);
+        steps.push(TraceStep {
+            line: i + 1,
+            instruction: line.trim().to_string(),
+            old,
+            new: dial.position(),
+            passes,
+            stopped: dial.position() == 0,
+        });
+    }
+    Ok(steps)
+}
+
+/// Writes the trace as CSV with a header row. With `zero_only`, only the
+/// steps that passed or stopped on 0 are written.
+pub fn write_trace_csv(
+    steps: &[TraceStep],
+    zero_only: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "line,instruction,old,new,passes,stopped")?;
+    for step in steps.iter().filter(|s| !zero_only || s.touches_zero()) {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            step.line, step.instruction, step.old, step.new, step.passes, step.stopped
+        )?;
+    }
+    Ok(())
+}
+
+pub fn solve01(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    let mut dials = [Dial::default()];
+    run_dials(lines, &mut dials)?;
+
+    println!("Part 1: {}", dials[0].part_1());
+    i64::try_from(dials[0].part_2()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+
+    #[test]
+    fn test_several_dials() {
+        let input = vec![Ok(String::from("R10")), Ok(String::from("L25"))];
+        let mut dials = [Dial::default(), Dial::new(10, 0), Dial::new(7, -1)];
+        run_dials(input.into_iter(), &mut dials).unwrap();
+        assert_eq!(dials[0].position(), 35);
+        assert_eq!(dials[1].position(), 5);
+        assert_eq!(dials[2].position(), 5);
+        assert_eq!(dials[1].stops, 1);
+    }
 
-        passes += count_crossings(old_pos, delta);
+    #[test]
+    fn test_huge_dial() {
+        let mut dial = Dial::new(i64::MAX, i64::MAX - 1);
+        dial.rotate(i64::MAX);
+        dial.rotate(i64::MIN + 1);
+        assert_eq!(dial.position(), i64::MAX - 1);
+    }
 
-        pos = (old_pos + delta).rem_euclid(100);
+    #[test]
+    fn test_example() {
+        let input = [
+            "L68", "L30", "R48", "L5", "R60", "L55", "L1", "L99", "R14", "L82",
+        ];
+        let mut dials = [Dial::default()];
+        run_dials(input.iter().map(|l| Ok(l.to_string())), &mut dials).unwrap();
+        assert_eq!(dials[0].part_1(), 3);
+        assert_eq!(dials[0].part_2(), 6);
+    }
 
-        if pos == 0 {
-            stops += 1;
+    #[test]
+    fn test_crossings_match_click_simulation() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let size = rng.range(1, 50);
+            let start = rng.range(0, size - 1);
+            let mut dial = Dial::new(size, start);
+            let mut pos = start;
+            let mut zeros: i128 = 0;
+            for _ in 0..20 {
+                // up to several full revolutions either way
+                let delta = rng.range(-5 * size, 5 * size);
+                dial.rotate(delta);
+                for _ in 0..delta.abs() {
+                    pos = (pos + delta.signum()).rem_euclid(size);
+                    if pos == 0 {
+                        zeros += 1;
+                    }
+                }
+                assert_eq!(dial.position(), pos);
+                assert_eq!(dial.part_2(), zeros, "size {} delta {}", size, delta);
+            }
         }
     }
 
-    Ok((stops + passes) as i64)
+    #[test]
+    fn test_trace_csv() {
+        let input = [
+            "L68", "L30", "R48", "L5", "R60", "L55", "L1", "L99", "R14", "L82",
+        ];
+        let steps = trace(input.iter().map(|l| Ok(l.to_string())), Dial::default()).unwrap();
+        assert_eq!(steps.len(), 10);
+        assert_eq!(
+            steps[0],
+            TraceStep {
+                line: 1,
+                instruction: String::from("L68"),
+                old: 50,
+                new: 82,
+                passes: 1,
+                stopped: false,
+            }
+        );
+
+        let mut out = Vec::new();
+        write_trace_csv(&steps, true, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[0], "line,instruction,old,new,passes,stopped");
+        assert_eq!(rows[2], "3,R48,52,0,0,true");
+        assert_eq!(rows[6], "10,L82,14,32,1,false");
+    }
 }
@@ -1,4 +1,5 @@
-use std::io;
+use std::collections::BTreeSet;
+use std::{fmt, io};
 
 fn is_invalid(s: &str) -> bool {
     let n = s.len();
@@ -29,34 +30,379 @@ fn all_chunks_equal(s: &str, n: usize) -> bool {
             .all(|chunk| chunk == &s[..n])
 }
 
-pub fn solve02(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut invalid_sum: i64 = 0;
-    let mut part_1: i64 = 0;
+/// `n` written in `base` (2 to 36) with lowercase digits, the string form
+/// `is_invalid` and `is_really_invalid` work on.
+fn to_base_string(mut n: u128, base: u32) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from_digit((n % base as u128) as u32, base).unwrap());
+        n /= base as u128;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+fn is_invalid_in_base(n: u128, base: u32) -> bool {
+    is_invalid(&to_base_string(n, base))
+}
+
+fn is_really_invalid_in_base(n: u128, base: u32) -> bool {
+    is_really_invalid(&to_base_string(n, base))
+}
+
+/// Number of digits of `n` in `base` (at least 1).
+fn digit_count(n: u128, base: u32) -> u32 {
+    n.max(1).ilog(base as u128) + 1
+}
+
+/// The `len`-digit numbers in `lo..=hi` made of one `period`-digit block
+/// repeated `len / period` times, written in `base`, are `block * rep` with
+/// `rep = 1 0..0 1 0..0 1` and `block` in the returned inclusive range
+/// (empty when `first > last`). Returns `(rep, first, last)`.
+fn repeated_blocks(lo: u128, hi: u128, len: u32, period: u32, base: u32) -> (u128, u128, u128) {
+    let base = base as u128;
+    let shift = base.pow(period);
+    // rep <= the largest len-digit number <= hi, so it cannot overflow
+    let rep = (0..len / period).fold(0u128, |acc, _| acc * shift + 1);
+    let first = base.pow(period - 1).max(lo.div_ceil(rep));
+    let last = (shift - 1).min(hi / rep);
+    (rep, first, last)
+}
+
+/// Sum of the numbers described by `repeated_blocks`: the blocks form an
+/// interval, so the sum is an arithmetic series. `None` if it overflows `u128`.
+fn sum_repeated(lo: u128, hi: u128, len: u32, period: u32, base: u32) -> Option<u128> {
+    let (rep, first, last) = repeated_blocks(lo, hi, len, period, base);
+    if first > last {
+        return Some(0);
+    }
+    // halve whichever factor is even before multiplying
+    let (mut sum, mut count) = (first + last, last - first + 1);
+    if sum % 2 == 0 {
+        sum /= 2;
+    } else {
+        count /= 2;
+    }
+    sum.checked_mul(count)?.checked_mul(rep)
+}
+
+/// Möbius function, for the small arguments that divide a digit count.
+fn mobius(mut n: u32) -> i128 {
+    let mut sign = 1;
+    let mut p = 2;
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            n /= p;
+            if n.is_multiple_of(p) {
+                return 0;
+            }
+            sign = -sign;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        sign = -sign;
+    }
+    sign
+}
+
+/// Sums of the invalid IDs in `lo..=hi`, written in `base`, for (part 1, part 2),
+/// without visiting every number: part 1 takes numbers made of two equal
+/// halves, part 2 any block repeated at least twice. A number like 111111 has
+/// periods 1, 2 and 3, so the per-period sums are combined by inclusion-exclusion
+/// over the divisors `d` of the length: the union of the period-`len / d` sets
+/// has weight `-mobius(d)`. `None` if a sum overflows `u128`.
+fn sum_invalid(lo: u128, hi: u128, base: u32) -> Option<(u128, u128)> {
+    let mut part_1: u128 = 0;
+    // positive and negative inclusion-exclusion terms, kept apart to stay unsigned
+    let mut added: u128 = 0;
+    let mut removed: u128 = 0;
+    for len in digit_count(lo, base)..=digit_count(hi, base) {
+        if len.is_multiple_of(2) {
+            part_1 = part_1.checked_add(sum_repeated(lo, hi, len, len / 2, base)?)?;
+        }
+        for d in (2..=len).filter(|&d| len.is_multiple_of(d)) {
+            let term = sum_repeated(lo, hi, len, len / d, base)?;
+            match mobius(d) {
+                -1 => added = added.checked_add(term)?,
+                1 => removed = removed.checked_add(term)?,
+                _ => {}
+            }
+        }
+    }
+    Some((part_1, added - removed))
+}
+
+fn overflow() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "sum of invalid IDs overflows u128",
+    )
+}
+
+/// Reads the comma-separated `lo-hi` ranges, with bounds in `base` (2 to 36).
+fn parse_ranges(
+    lines: impl Iterator<Item = io::Result<String>>,
+    base: u32,
+) -> io::Result<Vec<(u128, u128)>> {
+    if !(2..=36).contains(&base) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("base {} is not in 2..=36", base),
+        ));
+    }
+    let mut ranges = Vec::new();
     for line in lines {
         for part in line?.split(',') {
             let (a, b) = part
+                .trim()
                 .split_once('-')
                 .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad range"))?;
 
-            let n1: i64 = a
-                .parse()
+            let n1 = u128::from_str_radix(a, base)
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-            let n2: i64 = b
-                .parse()
+            let n2 = u128::from_str_radix(b, base)
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            ranges.push((n1, n2));
+        }
+    }
+    Ok(ranges)
+}
 
-            for i in n1..=n2 {
-                let n: String = i.to_string();
+/// Sums of invalid IDs for (part 1, part 2) with range bounds and repeated
+/// blocks read in `base` (2 to 36).
+pub fn sums_in_base(
+    lines: impl Iterator<Item = io::Result<String>>,
+    base: u32,
+) -> io::Result<(u128, u128)> {
+    let mut invalid_sum: u128 = 0;
+    let mut part_1: u128 = 0;
+    for (n1, n2) in parse_ranges(lines, base)? {
+        let (p1, p2) = sum_invalid(n1, n2, base).ok_or_else(overflow)?;
+        part_1 = part_1.checked_add(p1).ok_or_else(overflow)?;
+        invalid_sum = invalid_sum.checked_add(p2).ok_or_else(overflow)?;
+    }
+    Ok((part_1, invalid_sum))
+}
+
+/// Every invalid (part 2) ID in `lo..=hi`, ascending, generated from
+/// `repeated_blocks` rather than by scanning the range.
+fn invalid_ids(lo: u128, hi: u128, base: u32) -> Vec<u128> {
+    let mut ids = BTreeSet::new();
+    for len in digit_count(lo, base)..=digit_count(hi, base) {
+        for period in (1..len).filter(|&p| len.is_multiple_of(p)) {
+            let (rep, first, last) = repeated_blocks(lo, hi, len, period, base);
+            ids.extend((first..=last).map(|block| block * rep));
+        }
+    }
+    ids.into_iter().collect()
+}
+
+/// An invalid ID with its shortest repeating block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId {
+    pub id: u128,
+    /// Shortest block, in the report's base.
+    pub block: String,
+    pub repetitions: usize,
+    /// Made of two equal halves, so it counts for part 1 as well as part 2;
+    /// otherwise it is a part 2 only ID (three or more repetitions).
+    pub in_part_1: bool,
+}
+
+impl InvalidId {
+    fn new(id: u128, base: u32) -> Self {
+        let s = to_base_string(id, base);
+        let len = (1..=s.len()).find(|&n| all_chunks_equal(&s, n)).unwrap();
+        InvalidId {
+            id,
+            block: s[..len].to_string(),
+            repetitions: s.len() / len,
+            in_part_1: is_invalid(&s),
+        }
+    }
+}
+
+/// Invalid IDs found in one input range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeReport {
+    pub lo: u128,
+    pub hi: u128,
+    /// Base the bounds and IDs are displayed in.
+    pub base: u32,
+    pub ids: Vec<InvalidId>,
+}
+
+impl fmt::Display for RangeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}-{}:",
+            to_base_string(self.lo, self.base),
+            to_base_string(self.hi, self.base)
+        )?;
+        for id in &self.ids {
+            let parts = if id.in_part_1 {
+                "parts 1 and 2"
+            } else {
+                "part 2 only"
+            };
+            writeln!(
+                f,
+                "  {} = {} x {} ({})",
+                to_base_string(id.id, self.base),
+                id.block,
+                id.repetitions,
+                parts
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the invalid IDs of every range, classified by their shortest block.
+pub fn report_in_base(
+    lines: impl Iterator<Item = io::Result<String>>,
+    base: u32,
+) -> io::Result<Vec<RangeReport>> {
+    Ok(parse_ranges(lines, base)?
+        .into_iter()
+        .map(|(lo, hi)| RangeReport {
+            lo,
+            hi,
+            base,
+            ids: invalid_ids(lo, hi, base)
+                .into_iter()
+                .map(|id| InvalidId::new(id, base))
+                .collect(),
+        })
+        .collect())
+}
+
+pub fn solve02(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    let (part_1, invalid_sum) = sums_in_base(lines, 10)?;
+    println!("Part 1: {}", part_1);
+    i64::try_from(invalid_sum).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = vec![Ok(String::from(
+            "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,\
+1698522-1698528,446443-446449,38593856-38593862,565653-565659,\
+824824821-824824827,2121212118-2121212124",
+        ))];
+        let result = solve02(input.into_iter()).unwrap();
+        assert_eq!(result, 4174379265);
+    }
+
+    #[test]
+    fn test_closed_form_matches_brute_force() {
+        for (lo, hi) in [(1, 5000), (95, 115), (9990, 1_000_100), (111_110, 111_112)] {
+            let mut expected = (0, 0);
+            for i in lo..=hi {
+                let n = i.to_string();
                 if is_invalid(&n) {
-                    part_1 += i;
+                    expected.0 += i;
                 }
                 if is_really_invalid(&n) {
-                    // println!("{}-{}: {}", n1, n2, n);
-                    invalid_sum += i;
+                    expected.1 += i;
                 }
             }
+            assert_eq!(sum_invalid(lo, hi, 10), Some(expected), "{}-{}", lo, hi);
         }
     }
-    println!("Part 1: {}", part_1);
-    Ok(invalid_sum)
+
+    #[test]
+    fn test_other_bases() {
+        for base in [2, 3, 7, 16, 36] {
+            let mut expected = (0, 0);
+            for i in 1..=20_000u128 {
+                if is_invalid_in_base(i, base) {
+                    expected.0 += i;
+                }
+                if is_really_invalid_in_base(i, base) {
+                    expected.1 += i;
+                }
+            }
+            assert_eq!(
+                sum_invalid(1, 20_000, base),
+                Some(expected),
+                "base {}",
+                base
+            );
+        }
+        assert!(is_invalid_in_base(0b1010, 2));
+        assert!(is_really_invalid_in_base(0xabcabc, 16));
+        assert_eq!(to_base_string(123123, 10), "123123");
+    }
+
+    #[test]
+    fn test_u128_bounds() {
+        let big = "12345678123456781234567812345670-1234567812345678123456781234567f";
+        let input = vec![Ok(format!("ff-101,{}", big))];
+        let (part_1, part_2) = sums_in_base(input.into_iter(), 16).unwrap();
+        let id = 0x12345678123456781234567812345678u128;
+        assert_eq!(part_1, 0xff + id);
+        assert_eq!(part_2, 0xff + id);
+
+        // u128::MAX is all ones, so anything more overflows
+        let input = vec![Ok(String::from(
+            "ff-101,fffffffffffffffffffffffffffffff0-ffffffffffffffffffffffffffffffff",
+        ))];
+        assert!(sums_in_base(input.into_iter(), 16).is_err());
+        assert_eq!(sum_invalid(1, u128::MAX, 2), None);
+    }
+
+    #[test]
+    fn test_wide_range() {
+        // every "halves" number up to 18 digits, e.g. 11, 1010, 123123
+        let (part_1, _) = sum_invalid(1, 999_999_999_999_999_999, 10).unwrap();
+        assert_eq!(part_1, 495495495540950040450040950);
+        let (part_1, _) = sum_invalid(1, 99, 10).unwrap();
+        assert_eq!(part_1, (1..=9).map(|d| d * 11).sum::<u128>());
+    }
+
+    #[test]
+    fn test_report() {
+        let input = vec![Ok(String::from("95-115,998-1012,222220-222224"))];
+        let reports = report_in_base(input.into_iter(), 10).unwrap();
+        assert_eq!(reports.len(), 3);
+        let ids: Vec<(u128, &str, usize, bool)> = reports
+            .iter()
+            .flat_map(|r| &r.ids)
+            .map(|i| (i.id, i.block.as_str(), i.repetitions, i.in_part_1))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (99, "9", 2, true),
+                (111, "1", 3, false),
+                (999, "9", 3, false),
+                (1010, "10", 2, true),
+                (222222, "2", 6, true),
+            ]
+        );
+        assert_eq!(
+            reports[0].to_string(),
+            "95-115:\n  99 = 9 x 2 (parts 1 and 2)\n  111 = 1 x 3 (part 2 only)\n"
+        );
+
+        let input = vec![Ok(String::from("101-111"))];
+        let reports = report_in_base(input.into_iter(), 2).unwrap();
+        assert_eq!(
+            reports[0].to_string(),
+            "101-111:\n  111 = 1 x 3 (part 2 only)\n"
+        );
+
+        let input = vec![Ok(String::from("1-2"))];
+        let error = report_in_base(input.into_iter(), 37).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
 }
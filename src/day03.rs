@@ -1,74 +1,378 @@
-use std::cmp::max;
-use std::io;
+use std::{error, fmt, io};
 
-fn part_1(bytes: &[u8]) -> i64 {
+/// Problems with a bank line, located by 1-based line and column.
+#[derive(Debug, PartialEq)]
+pub enum BankError {
+    InvalidDigit {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    TooShort {
+        line: usize,
+        len: usize,
+        k: usize,
+    },
+    Unsatisfiable {
+        line: usize,
+        k: usize,
+    },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BankError::InvalidDigit {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {}, column {}: {:?} is not a digit",
+                line, column, found
+            ),
+            BankError::TooShort { line, len, k } => {
+                write!(f, "line {}: cannot pick {} digits from {}", line, k, len)
+            }
+            BankError::Unsatisfiable { line, k } => {
+                write!(
+                    f,
+                    "line {}: no {} digits satisfy the selection mode",
+                    line, k
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for BankError {}
+
+impl From<BankError> for io::Error {
+    fn from(e: BankError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Reads the banks, dropping trailing whitespace (such as the `\r` of a
+/// Windows line ending) and rejecting any other non-digit.
+fn read_banks(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Vec<String>> {
+    let mut banks = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        let bank = line.trim_end();
+        if let Some((column, found)) = bank.chars().enumerate().find(|(_, c)| !c.is_ascii_digit()) {
+            return Err(BankError::InvalidDigit {
+                line: i + 1,
+                column: column + 1,
+                found,
+            }
+            .into());
+        }
+        banks.push(bank.to_string());
+    }
+    Ok(banks)
+}
+
+/// Digits kept from one bank. The value is a decimal string so that any `k`
+/// fits; `as_u128` converts it when it is small enough.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub value: String,
+    pub indices: Vec<usize>,
+}
+
+impl Selection {
+    pub fn as_u128(&self) -> Option<u128> {
+        self.value.parse().ok()
+    }
+}
+
+/// Builds the selection of `indices` from `bytes`.
+fn selection(bytes: &[u8], indices: Vec<usize>) -> Selection {
+    let mut value: String = indices.iter().map(|&i| bytes[i] as char).collect();
+    if value.is_empty() {
+        value.push('0');
+    }
+    Selection { value, indices }
+}
+
+/// Keeps `k` of `bytes` with a monotonic stack: the current digit evicts the
+/// stack top while `evict(top, current)` holds and enough digits remain to
+/// still pick `k`. Every index is pushed and popped at most once (O(n)).
+/// Requires `k <= bytes.len()`.
+fn monotonic_pick(bytes: &[u8], k: usize, evict: impl Fn(u8, u8) -> bool) -> Vec<usize> {
     let n = bytes.len();
-    let mut m = 0;
+    let mut stack: Vec<usize> = Vec::with_capacity(k);
     for i in 0..n {
-        let d1 = (bytes[i] - b'0') as i64;
+        while let Some(&top) = stack.last() {
+            // popping still leaves enough digits: stack.len() - 1 + (n - i) >= k
+            if evict(bytes[top], bytes[i]) && stack.len() + n - i > k {
+                stack.pop();
+            } else {
+                break;
+            }
+        }
+        if stack.len() < k {
+            stack.push(i);
+        }
+    }
+    stack
+}
 
-        for j in i + 1..n {
-            let d2 = (bytes[j] - b'0') as i64;
+/// Largest number formed by keeping `k` of the digits in `bytes`, in order,
+/// together with the indices of the kept digits. `None` if there are fewer
+/// than `k` digits. `bytes` must be ASCII digits. O(n).
+fn max_subsequence(bytes: &[u8], k: usize) -> Option<Selection> {
+    if k > bytes.len() {
+        return None;
+    }
+    Some(selection(
+        bytes,
+        monotonic_pick(bytes, k, |top, cur| top < cur),
+    ))
+}
 
-            let num = d1 * 10 + d2; // two-digit number
-            m = max(m, num);
-        }
+/// Whether a selection may start with the digit 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeadingZeros {
+    Allow,
+    Forbid,
+}
+
+/// Smallest `k`-digit subsequence. With `LeadingZeros::Forbid` the first digit
+/// is the earliest smallest non-zero digit that leaves room for the rest;
+/// `None` if there is no such digit. O(n).
+fn min_subsequence(bytes: &[u8], k: usize, zeros: LeadingZeros) -> Option<Selection> {
+    let n = bytes.len();
+    if k > n {
+        return None;
+    }
+    let mut picked = Vec::with_capacity(k);
+    let mut offset = 0;
+    if zeros == LeadingZeros::Forbid && k > 0 {
+        let first = (0..=n - k)
+            .filter(|&i| bytes[i] != b'0')
+            .min_by_key(|&i| (bytes[i], i))?;
+        picked.push(first);
+        offset = first + 1;
     }
-    m
+    let rest = monotonic_pick(&bytes[offset..], k - picked.len(), |top, cur| top > cur);
+    picked.extend(rest.into_iter().map(|i| i + offset));
+    Some(selection(bytes, picked))
 }
 
-const DEPTH: usize = 12;
+/// Largest `k`-digit subsequence whose consecutive chosen indices are at most
+/// `max_gap` apart. `best[r][i]` is the best `r`-digit pick starting at `i`,
+/// extended from the best `r - 1`-digit pick within reach: O(n k max_gap k).
+fn max_with_gap(bytes: &[u8], k: usize, max_gap: usize) -> Option<Selection> {
+    let n = bytes.len();
+    if k == 0 {
+        return Some(selection(bytes, vec![]));
+    }
+    let digits = |idx: &Vec<usize>| idx.iter().map(|&i| bytes[i]).collect::<Vec<u8>>();
+
+    let mut best: Vec<Option<Vec<usize>>> = (0..n).map(|i| Some(vec![i])).collect();
+    for _ in 1..k {
+        best = (0..n)
+            .map(|i| {
+                let reach = (i + max_gap).min(n.saturating_sub(1));
+                let tail = ((i + 1)..=reach)
+                    .filter_map(|j| best[j].as_ref())
+                    .max_by_key(|idx| digits(idx))?;
+                let mut idx = vec![i];
+                idx.extend(tail);
+                Some(idx)
+            })
+            .collect();
+    }
+    let idx = best.into_iter().flatten().max_by_key(|idx| digits(idx))?;
+    Some(selection(bytes, idx))
+}
 
-// Recursive function to find the maximum number by either including or skipping digits
-fn part_2(bytes: &[u8]) -> i64 {
+/// Largest `k`-digit subsequence whose value is a multiple of `modulus`.
+/// `feasible[i][r][rem]` says whether `bytes[i..]` has an `r`-digit subsequence
+/// congruent to `rem`; the answer is then built greedily, taking the largest
+/// digit (at its earliest position) that keeps the remainder reachable.
+/// O(n k modulus) time and memory.
+fn max_divisible(bytes: &[u8], k: usize, modulus: usize) -> Option<Selection> {
+    assert!(modulus > 0, "modulus must be positive");
     let n = bytes.len();
-    let mut max_value: i64 = 0;
-    let mut remaining_digits = DEPTH;
-    let mut index = 0;
-
-    // Greedily select digits, considering from left to right
-    while remaining_digits > 0 {
-        let mut max_digit = 0;
-        let mut max_digit_index = index;
-
-        // Search for the max digit within the valid range
-        for i in index..=n - remaining_digits {
-            let digit = (bytes[i] - b'0') as i64;
-            if digit > max_digit {
-                max_digit = digit;
-                max_digit_index = i;
+    if k > n {
+        return None;
+    }
+    // pow[r] = 10^r mod modulus
+    let mut pow = vec![1 % modulus; k + 1];
+    for r in 1..=k {
+        pow[r] = pow[r - 1] * 10 % modulus;
+    }
+    // remainder still needed from the other r - 1 digits after placing `d` first
+    let need = |rem: usize, d: u8, r: usize| {
+        (rem + modulus - (d - b'0') as usize * pow[r - 1] % modulus) % modulus
+    };
+
+    let at = |i: usize, r: usize, rem: usize| (i * (k + 1) + r) * modulus + rem;
+    let mut feasible = vec![false; (n + 1) * (k + 1) * modulus];
+    for i in (0..=n).rev() {
+        feasible[at(i, 0, 0)] = true;
+        if i == n {
+            continue;
+        }
+        for r in 1..=k {
+            for rem in 0..modulus {
+                feasible[at(i, r, rem)] = feasible[at(i + 1, r, rem)]
+                    || feasible[at(i + 1, r - 1, need(rem, bytes[i], r))];
             }
         }
+    }
+    if !feasible[at(0, k, 0)] {
+        return None;
+    }
 
-        // Build the number by appending the max digit found
-        max_value = max_value * 10 + max_digit;
+    let mut picked = Vec::with_capacity(k);
+    let (mut i, mut rem) = (0, 0);
+    for r in (1..=k).rev() {
+        let j = (b'0'..=b'9')
+            .rev()
+            .find_map(|d| {
+                (i..n).find(|&j| bytes[j] == d && feasible[at(j + 1, r - 1, need(rem, d, r))])
+            })
+            .expect("feasible table guarantees a digit");
+        rem = need(rem, bytes[j], r);
+        picked.push(j);
+        i = j + 1;
+    }
+    Some(selection(bytes, picked))
+}
 
-        // Update index to continue from the next position after the selected digit
-        index = max_digit_index + 1;
-        remaining_digits -= 1;
+/// Sum of two non-negative decimal strings.
+fn add_decimal(a: &str, b: &str) -> String {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut digits = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let da = if i < a.len() {
+            a[a.len() - 1 - i] - b'0'
+        } else {
+            0
+        };
+        let db = if i < b.len() {
+            b[b.len() - 1 - i] - b'0'
+        } else {
+            0
+        };
+        let d = da + db + carry;
+        digits.push(b'0' + d % 10);
+        carry = d / 10;
+    }
+    if carry > 0 {
+        digits.push(b'0' + carry);
+    }
+    while digits.len() > 1 && digits.last() == Some(&b'0') {
+        digits.pop();
     }
-    max_value
+    digits.iter().rev().map(|&d| d as char).collect()
 }
 
-pub fn solve03(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut total: i64 = 0;
+/// Objective used to pick the `k` digits of a bank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Max,
+    Min(LeadingZeros),
+    /// Largest value with chosen indices at most this far apart.
+    MaxGap(usize),
+    /// Largest value that is a multiple of this modulus.
+    MaxDivisible(usize),
+}
 
-    for (i, line) in lines.into_iter().enumerate() {
-        println!("Line {}", i);
-        let line = line?;
-        let bytes = line.as_bytes();
-        println!("part 1 {}", part_1(&bytes));
-        let m = part_2(&bytes);
+/// Picks `k` digits of `bytes` according to `mode`; `None` if no selection
+/// satisfies it.
+pub fn select(bytes: &[u8], k: usize, mode: Mode) -> Option<Selection> {
+    match mode {
+        Mode::Max => max_subsequence(bytes, k),
+        Mode::Min(zeros) => min_subsequence(bytes, k, zeros),
+        Mode::MaxGap(gap) => max_with_gap(bytes, k, gap),
+        Mode::MaxDivisible(modulus) => max_divisible(bytes, k, modulus),
+    }
+}
+
+fn part_1(bytes: &[u8]) -> i64 {
+    max_subsequence(bytes, 2).map_or(0, |s| s.value.parse().unwrap())
+}
 
-        total += m; // Add the result for the current line
+const DEPTH: usize = 12;
+
+/// Chosen digits of every bank and their total, for a given `k`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub banks: Vec<Selection>,
+    pub total: String,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, bank) in self.banks.iter().enumerate() {
+            writeln!(f, "Line {}: {} at {:?}", i + 1, bank.value, bank.indices)?;
+        }
+        writeln!(f, "Total: {}", self.total)
+    }
+}
+
+fn report(banks: &[String], k: usize, mode: Mode) -> Result<Report, BankError> {
+    let mut selections = Vec::with_capacity(banks.len());
+    let mut total = String::from("0");
+    for (i, bank) in banks.iter().enumerate() {
+        if bank.len() < k {
+            return Err(BankError::TooShort {
+                line: i + 1,
+                len: bank.len(),
+                k,
+            });
+        }
+        let selection =
+            select(bank.as_bytes(), k, mode).ok_or(BankError::Unsatisfiable { line: i + 1, k })?;
+        total = add_decimal(&total, &selection.value);
+        selections.push(selection);
     }
+    Ok(Report {
+        banks: selections,
+        total,
+    })
+}
+
+/// Solves part 2 picking `depth` digits per bank; the total is returned as a
+/// decimal string since it outgrows every integer type for large `depth`.
+pub fn solve03_with_depth(
+    lines: impl Iterator<Item = io::Result<String>>,
+    depth: usize,
+) -> io::Result<String> {
+    solve03_with_mode(lines, depth, Mode::Max)
+}
+
+/// Like `solve03_with_depth`, choosing the part 2 digits by `mode`.
+pub fn solve03_with_mode(
+    lines: impl Iterator<Item = io::Result<String>>,
+    depth: usize,
+    mode: Mode,
+) -> io::Result<String> {
+    let banks = read_banks(lines)?;
+    println!("Part 1: {}", report(&banks, 2, Mode::Max)?.total);
+    let report = report(&banks, depth, mode)?;
+    print!("{}", report);
+    Ok(report.total)
+}
 
-    Ok(total)
+pub fn solve03(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    solve03_with_depth(lines, DEPTH)?
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::XorShift;
+    use itertools::Itertools;
 
     #[test]
     fn test_part_1_case_1() {
@@ -119,4 +423,113 @@ mod tests {
         let result = solve03(input.into_iter()).unwrap();
         assert_eq!(result, 434234234278);
     }
+
+    #[test]
+    fn test_max_subsequence_indices() {
+        let selection = max_subsequence(b"818181911112111", 12).unwrap();
+        assert_eq!(selection.as_u128(), Some(888911112111));
+        assert_eq!(
+            selection.indices,
+            vec![0, 2, 4, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+        );
+        assert_eq!(max_subsequence(b"12", 2).unwrap().indices, vec![0, 1]);
+        assert_eq!(max_subsequence(b"12", 3), None);
+    }
+
+    #[test]
+    fn test_large_depth_and_validation() {
+        let input = vec![
+            Ok(String::from("98765432109876543210999\r")),
+            Ok(String::from("11111111111111111111111")),
+        ];
+        let total = solve03_with_depth(input.into_iter(), 22).unwrap();
+        assert_eq!(
+            total,
+            add_decimal("9876543219876543210999", "1111111111111111111111")
+        );
+        assert_eq!(total, "10987654330987654322110");
+
+        let input = vec![Ok(String::from("12345")), Ok(String::from("98x1"))];
+        let err = solve03(input.into_iter()).unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<BankError>().unwrap();
+        assert_eq!(
+            *inner,
+            BankError::InvalidDigit {
+                line: 2,
+                column: 3,
+                found: 'x'
+            }
+        );
+
+        let input = vec![Ok(String::from("12345"))];
+        assert!(solve03(input.into_iter()).is_err());
+    }
+
+    /// Random bank of `len` battery digits.
+    fn random_bank(rng: &mut XorShift, len: usize) -> Vec<u8> {
+        (0..len).map(|_| b'0' + rng.range(0, 9) as u8).collect()
+    }
+
+    /// Digit strings of every `k`-subset of `bytes` accepted by `keep`.
+    fn brute(bytes: &[u8], k: usize, keep: impl Fn(&[usize]) -> bool) -> Vec<String> {
+        (0..bytes.len())
+            .combinations(k)
+            .filter(|idx| keep(idx))
+            .map(|idx| selection(bytes, idx).value)
+            .collect()
+    }
+
+    #[test]
+    fn test_selection_modes_match_brute_force() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let n = 1 + (rng.next() % 10) as usize;
+            let bytes = random_bank(&mut rng, n);
+            for k in 1..=n {
+                let all = brute(&bytes, k, |_| true);
+                assert_eq!(
+                    max_subsequence(&bytes, k).unwrap().value,
+                    *all.iter().max().unwrap()
+                );
+                assert_eq!(
+                    min_subsequence(&bytes, k, LeadingZeros::Allow)
+                        .unwrap()
+                        .value,
+                    *all.iter().min().unwrap()
+                );
+                assert_eq!(
+                    min_subsequence(&bytes, k, LeadingZeros::Forbid).map(|s| s.value),
+                    all.iter().filter(|v| !v.starts_with('0')).min().cloned()
+                );
+
+                let gap = 1 + (rng.next() % 3) as usize;
+                let within = |idx: &[usize]| idx.windows(2).all(|w| w[1] - w[0] <= gap);
+                assert_eq!(
+                    max_with_gap(&bytes, k, gap).map(|s| s.value),
+                    brute(&bytes, k, within).into_iter().max()
+                );
+
+                let m = 1 + (rng.next() % 13) as usize;
+                let divisible = all
+                    .iter()
+                    .filter(|v| v.parse::<u64>().unwrap() % m as u64 == 0)
+                    .max()
+                    .cloned();
+                assert_eq!(max_divisible(&bytes, k, m).map(|s| s.value), divisible);
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_modes() {
+        let banks = vec![String::from("1290"), String::from("3071")];
+        let min = report(&banks, 2, Mode::Min(LeadingZeros::Forbid)).unwrap();
+        assert_eq!(min.banks[0].value, "10");
+        assert_eq!(min.banks[1].value, "30");
+        assert_eq!(min.total, "40");
+        assert_eq!(
+            report(&banks, 2, Mode::MaxDivisible(11)),
+            Err(BankError::Unsatisfiable { line: 1, k: 2 })
+        );
+    }
 }
@@ -1,117 +1,583 @@
-use std::io::{self};
-
-const POSITIONS: [(isize, isize); 8] = [
-    (-1, -1),
-    (0, -1),
-    (1, -1),
-    (-1, 0),
-    (1, 0),
-    (-1, 1),
-    (0, 1),
-    (1, 1),
-];
+use itertools::Itertools;
+use std::io::{self, Write};
+
 const BYTE_OF_AT: u8 = b'@'; // This is a byte literal for '@'
 
-fn explore(matrix: &Vec<Vec<u8>>, i: usize, j: usize, n_rows: usize, n_cols: usize) -> bool {
-    let mut count: usize = 0;
-    for (x, y) in POSITIONS {
-        let row = i as isize + x;
-        if row < 0 || row >= n_rows as isize {
-            continue;
+/// Cells counted as neighbours of a roll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The 4 orthogonally adjacent cells.
+    VonNeumann,
+    /// The 8 surrounding cells.
+    #[default]
+    Moore,
+    /// Every cell within Chebyshev distance `r`.
+    Radius(usize),
+    /// The 6 neighbours of a hexagonal grid in axial coordinates, i.e. with
+    /// each row shifted half a cell right of the one above it.
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    /// Offsets of the neighbours in `dim` dimensions, generated rather than
+    /// tabulated. The hexagonal neighbourhood only exists in 2-D; asking for
+    /// it in any other dimension is an `InvalidInput` error.
+    fn offsets(&self, dim: usize) -> io::Result<Vec<Vec<isize>>> {
+        let cube = |r: isize| {
+            (0..dim)
+                .map(|_| -r..=r)
+                .multi_cartesian_product()
+                .filter(|offset| offset.iter().any(|&x| x != 0))
+                .collect()
+        };
+        Ok(match *self {
+            Neighbourhood::VonNeumann => (0..dim)
+                .flat_map(|axis| {
+                    [-1, 1].into_iter().map(move |step| {
+                        let mut offset = vec![0; dim];
+                        offset[axis] = step;
+                        offset
+                    })
+                })
+                .collect(),
+            Neighbourhood::Moore => cube(1),
+            Neighbourhood::Radius(r) => cube(r as isize),
+            Neighbourhood::Hexagonal if dim == 2 => {
+                [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)]
+                    .into_iter()
+                    .map(|(x, y)| vec![x, y])
+                    .collect()
+            }
+            Neighbourhood::Hexagonal => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "the hexagonal neighbourhood is 2-D only, the grid is {}-D",
+                        dim
+                    ),
+                ))
+            }
+        })
+    }
+}
+
+/// What lies beyond the grid edges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Topology {
+    /// Nothing: cells outside the grid are never rolls.
+    #[default]
+    Bounded,
+    /// The grid wraps around along every axis.
+    Toroidal,
+}
+
+/// A roll is accessible when fewer than `threshold` of its neighbours are
+/// rolls. The default is the puzzle rule: Moore neighbourhood, threshold 4,
+/// bounded grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub neighbourhood: Neighbourhood,
+    pub threshold: usize,
+    pub topology: Topology,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            neighbourhood: Neighbourhood::default(),
+            threshold: 4,
+            topology: Topology::default(),
         }
-        let col = j as isize + y;
-        if col < 0 || col >= n_cols as isize {
-            continue;
+    }
+}
+
+const BYTE_OF_REMOVED: u8 = b'x';
+
+/// The full removal process: the starting grid, the cells removed in each
+/// wave and the grid left once nothing more is accessible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Erosion {
+    pub initial: Vec<Vec<u8>>,
+    pub waves: Vec<Vec<(usize, usize)>>,
+    pub stable: Vec<Vec<u8>>,
+}
+
+impl Erosion {
+    /// Reads a 2-D grid and runs the removal on it. Layered (3-D) input is
+    /// rejected, as the frames are drawn as flat grids.
+    pub fn from_lines(
+        lines: impl Iterator<Item = io::Result<String>>,
+        rule: &Rule,
+    ) -> io::Result<Self> {
+        let grid = GridN::from_lines(lines)?;
+        let &[n_rows, n_cols] = grid.dims() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "erosion frames need a 2-D grid, got {}-D",
+                    grid.dims().len()
+                ),
+            ));
+        };
+        let initial: Vec<Vec<u8>> = (0..n_rows)
+            .map(|i| {
+                (0..n_cols)
+                    .map(|j| {
+                        if grid.is_roll(i * n_cols + j) {
+                            BYTE_OF_AT
+                        } else {
+                            b'.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let waves: Vec<Vec<(usize, usize)>> = grid
+            .clone()
+            .peel(rule)?
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|k| (k / n_cols, k % n_cols)).collect())
+            .collect();
+        let mut stable = initial.clone();
+        for &(i, j) in waves.iter().flatten() {
+            stable[i][j] = b'.';
+        }
+        Ok(Erosion {
+            initial,
+            waves,
+            stable,
+        })
+    }
+
+    /// Number of rolls removed in each wave.
+    pub fn counts(&self) -> Vec<usize> {
+        self.waves.iter().map(|wave| wave.len()).collect()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.waves.iter().map(|wave| wave.len()).sum()
+    }
+
+    /// The grid as it looks during wave `wave` (1-based), with the rolls it
+    /// removes marked `x`; frame 0 is the initial grid.
+    pub fn frame(&self, wave: usize) -> Vec<Vec<u8>> {
+        let mut grid = self.initial.clone();
+        for (k, cells) in self.waves.iter().take(wave).enumerate() {
+            let mark = if k + 1 == wave { BYTE_OF_REMOVED } else { b'.' };
+            for &(i, j) in cells {
+                grid[i][j] = mark;
+            }
         }
-        if matrix[row as usize][col as usize] == BYTE_OF_AT {
-            count += 1;
-            if count >= 4 {
-                return false;
+        grid
+    }
+
+    /// Writes every frame as ASCII, each preceded by a header line and
+    /// followed by a blank line, ending with the stable grid.
+    pub fn write_frames(&self, out: &mut impl Write) -> io::Result<()> {
+        for wave in 0..=self.waves.len() {
+            if wave == 0 {
+                writeln!(out, "Initial state:")?;
+            } else {
+                let removed = self.waves[wave - 1].len();
+                writeln!(out, "Wave {}: remove {} rolls", wave, removed)?;
+            }
+            for row in self.frame(wave) {
+                out.write_all(&row)?;
+                writeln!(out)?;
             }
+            writeln!(out)?;
+        }
+        writeln!(out, "Stable:")?;
+        for row in &self.stable {
+            out.write_all(row)?;
+            writeln!(out)?;
         }
+        Ok(())
     }
+}
 
-    true
+/// A grid of any dimension, stored flat in row-major order (the last axis
+/// varies fastest).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridN {
+    dims: Vec<usize>,
+    rolls: Vec<bool>,
 }
 
-fn part2(matrix: &mut Vec<Vec<u8>>) -> usize {
-    let mut ans = 0;
-    let n = matrix.len();
-    let m = matrix[0].len();
-
-    let max_it = 50000;
-    for _ in 0..max_it {
-        let mut idxs: Vec<(usize, usize)> = vec![];
-        for i in 0..n {
-            for j in 0..m {
-                let ch = matrix[i][j];
-                if ch == BYTE_OF_AT && explore(&matrix, i, j, n, m) {
-                    idxs.push((i, j));
+impl GridN {
+    pub fn new(dims: Vec<usize>, rolls: Vec<bool>) -> Self {
+        assert_eq!(
+            dims.iter().product::<usize>(),
+            rolls.len(),
+            "cell count does not match the dimensions"
+        );
+        GridN { dims, rolls }
+    }
+
+    /// Reads 2-D layers separated by blank lines. A single layer gives a 2-D
+    /// grid, several give a 3-D grid indexed `[layer, row, col]`.
+    pub fn from_lines(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Self> {
+        let mut layers: Vec<Vec<String>> = vec![vec![]];
+        for line in lines {
+            let line = line?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                if !layers.last().unwrap().is_empty() {
+                    layers.push(vec![]);
                 }
+            } else {
+                layers.last_mut().unwrap().push(line.to_string());
             }
         }
-        if idxs.len() == 0 {
-            break;
+        if layers.last().unwrap().is_empty() {
+            layers.pop();
         }
-        ans += idxs.len();
-        // modify matrix
-        for (i, j) in idxs {
-            matrix[i][j] = b'.';
+
+        let n_rows = layers.first().map_or(0, |layer| layer.len());
+        let n_cols = layers
+            .first()
+            .and_then(|layer| layer.first())
+            .map_or(0, |row| row.len());
+        let mut rolls = Vec::with_capacity(layers.len() * n_rows * n_cols);
+        for (l, layer) in layers.iter().enumerate() {
+            if layer.len() != n_rows {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "layer {} has {} rows, expected {}",
+                        l + 1,
+                        layer.len(),
+                        n_rows
+                    ),
+                ));
+            }
+            for (r, row) in layer.iter().enumerate() {
+                if row.len() != n_cols {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "layer {}, row {} has {} columns, expected {}",
+                            l + 1,
+                            r + 1,
+                            row.len(),
+                            n_cols
+                        ),
+                    ));
+                }
+                rolls.extend(row.bytes().map(|b| b == BYTE_OF_AT));
+            }
         }
+
+        let dims = if layers.len() == 1 {
+            vec![n_rows, n_cols]
+        } else {
+            vec![layers.len(), n_rows, n_cols]
+        };
+        Ok(GridN::new(dims, rolls))
     }
-    ans
-}
 
-pub fn solve04(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut ans = 0;
-
-    // Collect the lines to calculate NUM_ROWS and NUM_COLS
-    let matrix: Vec<String> = lines.collect::<Result<Vec<String>, _>>()?;
-
-    // Convert Vec<String> into Vec<Vec<u8>> (Vec of byte vectors)
-    let mut matrix_bytes: Vec<Vec<u8>> = matrix
-        .into_iter()
-        .map(|s| s.into_bytes()) // Convert each String to Vec<u8>
-        .collect();
-
-    // check the matrix based on the lines
-    let n = matrix_bytes.len();
-    let m = matrix_bytes[0].len();
-    for i in 0..n {
-        for j in 0..m {
-            let ch = matrix_bytes[i][j];
-            if ch == BYTE_OF_AT && explore(&matrix_bytes, i, j, n, m) {
-                ans += 1;
+    pub fn dims(&self) -> &[usize] {
+        &self.dims
+    }
+
+    pub fn is_roll(&self, index: usize) -> bool {
+        self.rolls[index]
+    }
+
+    /// Flat indices of the neighbours of the cell at flat `index`, each
+    /// listed once and never the cell itself.
+    fn around(&self, offsets: &[Vec<isize>], topology: Topology, index: usize) -> Vec<usize> {
+        let mut coords = vec![0; self.dims.len()];
+        let mut rest = index;
+        for (axis, &size) in self.dims.iter().enumerate().rev() {
+            coords[axis] = rest % size;
+            rest /= size;
+        }
+        let mut targets: Vec<usize> = offsets
+            .iter()
+            .filter_map(|offset| {
+                let mut target = 0;
+                for ((&c, &o), &size) in coords.iter().zip(offset).zip(&self.dims) {
+                    let moved = match topology {
+                        Topology::Bounded => c.checked_add_signed(o).filter(|&x| x < size)?,
+                        Topology::Toroidal => (c as isize + o).rem_euclid(size as isize) as usize,
+                    };
+                    target = target * size + moved;
+                }
+                Some(target)
+            })
+            .collect();
+        if topology == Topology::Toroidal {
+            // on an axis shorter than the neighbourhood, offsets wrap onto
+            // the same cell or back onto this one
+            targets.sort_unstable();
+            targets.dedup();
+            targets.retain(|&k| k != index);
+        }
+        targets
+    }
+
+    fn count(&self, offsets: &[Vec<isize>], topology: Topology, index: usize) -> usize {
+        self.around(offsets, topology, index)
+            .into_iter()
+            .filter(|&k| self.rolls[k])
+            .count()
+    }
+
+    /// Rolls accessible under `rule` before anything is removed.
+    pub fn accessible(&self, rule: &Rule) -> io::Result<usize> {
+        let offsets = rule.neighbourhood.offsets(self.dims.len())?;
+        Ok((0..self.rolls.len())
+            .filter(|&k| self.rolls[k] && self.count(&offsets, rule.topology, k) < rule.threshold)
+            .count())
+    }
+
+    /// Removes accessible rolls wave by wave until none is left, returning
+    /// the flat indices removed in each wave. Neighbour counts are computed
+    /// once and then decremented on removal; a roll joins the next wave the
+    /// moment its count drops below the threshold, so every cell is touched
+    /// a constant number of times per neighbour.
+    pub fn peel(&mut self, rule: &Rule) -> io::Result<Vec<Vec<usize>>> {
+        let offsets = rule.neighbourhood.offsets(self.dims.len())?;
+        let mut counts = vec![0usize; self.rolls.len()];
+        let mut wave = vec![];
+        for (k, count) in counts.iter_mut().enumerate() {
+            if self.rolls[k] {
+                *count = self.count(&offsets, rule.topology, k);
+                if *count < rule.threshold {
+                    wave.push(k);
+                }
             }
         }
+
+        let mut waves = vec![];
+        while !wave.is_empty() {
+            for &k in &wave {
+                self.rolls[k] = false;
+            }
+            let mut next = vec![];
+            for &k in &wave {
+                for n in self.around(&offsets, rule.topology, k) {
+                    if !self.rolls[n] {
+                        continue;
+                    }
+                    counts[n] -= 1;
+                    // rolls already below the threshold are queued in this wave
+                    if counts[n] + 1 == rule.threshold {
+                        next.push(n);
+                    }
+                }
+            }
+            waves.push(wave);
+            wave = next;
+        }
+        Ok(waves)
     }
+}
+
+pub fn solve04(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    solve04_with_rule(lines, &Rule::default())
+}
+
+/// Solves both parts with a custom accessibility rule, on a 2-D grid or on
+/// blank-line separated layers of a 3-D one.
+pub fn solve04_with_rule(
+    lines: impl Iterator<Item = io::Result<String>>,
+    rule: &Rule,
+) -> io::Result<i64> {
+    let grid = GridN::from_lines(lines)?;
+    let ans = grid.accessible(rule)?;
 
     // part 2
-    let part_2 = part2(&mut matrix_bytes);
+    let part_2: usize = grid.clone().peel(rule)?.iter().map(|wave| wave.len()).sum();
     println!("Part 2: {}", part_2);
 
-    Ok(ans)
+    Ok(ans as i64)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: [&str; 10] = [
+        "..@@.@@@@.",
+        "@@@.@.@.@@",
+        "@@@@@.@.@@",
+        "@.@@@@..@.",
+        "@@.@@@@.@@",
+        ".@@@@@@@.@",
+        ".@.@.@.@@@",
+        "@.@@@.@@@@",
+        ".@@@@@@@@.",
+        "@.@.@@@.@.",
+    ];
+
+    fn example_lines() -> impl Iterator<Item = io::Result<String>> {
+        EXAMPLE.iter().map(|l| Ok(l.to_string()))
+    }
+
+    /// Reference for `peel`: rescans the whole grid after every wave.
+    /// Returns the waves in increasing index order and the grid left.
+    fn rescan_waves(grid: &GridN, rule: &Rule) -> (Vec<Vec<usize>>, GridN) {
+        let offsets = rule.neighbourhood.offsets(grid.dims.len()).unwrap();
+        let mut rescanned = grid.clone();
+        let mut waves = vec![];
+        loop {
+            let wave: Vec<usize> = (0..rescanned.rolls.len())
+                .filter(|&k| {
+                    rescanned.rolls[k]
+                        && rescanned.count(&offsets, rule.topology, k) < rule.threshold
+                })
+                .collect();
+            if wave.is_empty() {
+                break;
+            }
+            wave.iter().for_each(|&k| rescanned.rolls[k] = false);
+            waves.push(wave);
+        }
+        (waves, rescanned)
+    }
+
     #[test]
     fn test_part_1() {
-        let input = vec![
-            Ok(String::from("..@@.@@@@.")),
-            Ok(String::from("@@@.@.@.@@")),
-            Ok(String::from("@@@@@.@.@@")),
-            Ok(String::from("@.@@@@..@.")),
-            Ok(String::from("@@.@@@@.@@")),
-            Ok(String::from(".@@@@@@@.@")),
-            Ok(String::from(".@.@.@.@@@")),
-            Ok(String::from("@.@@@.@@@@")),
-            Ok(String::from(".@@@@@@@@.")),
-            Ok(String::from("@.@.@@@.@.")),
-        ];
-        let result = solve04(input.into_iter()).unwrap();
+        let result = solve04(example_lines()).unwrap();
         assert_eq!(result, 13);
     }
+
+    #[test]
+    fn test_peel_waves_match_rescan() {
+        let grid = GridN::from_lines(example_lines()).unwrap();
+        let rule = Rule::default();
+        let (expected, rescanned) = rescan_waves(&grid, &rule);
+
+        let mut peeled = grid.clone();
+        let mut waves = peeled.peel(&rule).unwrap();
+        waves.iter_mut().for_each(|wave| wave.sort());
+        assert_eq!(waves, expected);
+        assert_eq!(peeled, rescanned);
+        assert_eq!(waves.iter().map(|wave| wave.len()).sum::<usize>(), 43);
+    }
+
+    #[test]
+    fn test_rules() {
+        let grid = ["@@@", "@@@", "@@@"];
+        let lines = || grid.iter().map(|l| Ok(l.to_string()));
+        let rule = |neighbourhood, threshold, topology| Rule {
+            neighbourhood,
+            threshold,
+            topology,
+        };
+
+        // bounded Moore: only the 4 corners have 3 neighbours
+        assert_eq!(solve04(lines()).unwrap(), 4);
+        // von Neumann: corners have 2 neighbours, edges 3, the centre 4
+        let von_neumann = rule(Neighbourhood::VonNeumann, 3, Topology::Bounded);
+        assert_eq!(solve04_with_rule(lines(), &von_neumann).unwrap(), 4);
+        // on a 3x3 torus every roll sees all 8 others
+        let torus = rule(Neighbourhood::Moore, 8, Topology::Toroidal);
+        assert_eq!(solve04_with_rule(lines(), &torus).unwrap(), 0);
+        let torus = rule(Neighbourhood::Moore, 9, Topology::Toroidal);
+        assert_eq!(solve04_with_rule(lines(), &torus).unwrap(), 9);
+        // radius 2 on a bounded 3x3 grid reaches everything from every cell
+        let wide = rule(Neighbourhood::Radius(2), 8, Topology::Bounded);
+        assert_eq!(solve04_with_rule(lines(), &wide).unwrap(), 0);
+        // hexagonal: two opposite corners have 2 neighbours, the other two 3
+        let hex = rule(Neighbourhood::Hexagonal, 3, Topology::Bounded);
+        assert_eq!(solve04_with_rule(lines(), &hex).unwrap(), 2);
+        // a 1x2 torus: each roll's only neighbour is the other one
+        let pair = GridN::from_lines([Ok(String::from("@@"))].into_iter()).unwrap();
+        let moore = Neighbourhood::Moore.offsets(2).unwrap();
+        assert_eq!(pair.around(&moore, Topology::Toroidal, 0), vec![1]);
+        let torus = rule(Neighbourhood::Moore, 2, Topology::Toroidal);
+        assert_eq!(pair.accessible(&torus).unwrap(), 2);
+        // radius 2 wraps all the way around a 3x3 torus
+        let wide = Neighbourhood::Radius(2).offsets(2).unwrap();
+        let square = GridN::from_lines(lines()).unwrap();
+        assert_eq!(square.around(&wide, Topology::Toroidal, 4).len(), 8);
+        assert_eq!(Neighbourhood::Moore.offsets(2).unwrap().len(), 8);
+        assert_eq!(
+            Neighbourhood::Radius(1).offsets(2).unwrap(),
+            Neighbourhood::Moore.offsets(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_erosion_frames() {
+        let lines = |input: &[&str]| input.iter().map(|l| Ok(l.to_string())).collect_vec();
+        let erosion =
+            Erosion::from_lines(lines(&["@@@", "@@@", "@@@"]).into_iter(), &Rule::default())
+                .unwrap();
+        // corners first, then the edges, then the centre
+        assert_eq!(erosion.counts(), vec![4, 4, 1]);
+        assert_eq!(erosion.removed(), 9);
+        assert_eq!(
+            erosion.frame(2),
+            vec![b".x.".to_vec(), b"x@x".to_vec(), b".x.".to_vec()]
+        );
+        assert!(erosion.stable.iter().flatten().all(|&b| b == b'.'));
+
+        let mut out = Vec::new();
+        erosion.write_frames(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("Initial state:\n@@@\n@@@\n@@@\n\nWave 1: remove 4 rolls\nx@x\n"));
+        assert!(text.ends_with("Wave 3: remove 1 rolls\n...\n.x.\n...\n\nStable:\n...\n...\n...\n"));
+
+        let ragged = lines(&["@@@", "@@"]).into_iter();
+        assert!(Erosion::from_lines(ragged, &Rule::default()).is_err());
+        let layered = lines(&["@@", "", "@@"]).into_iter();
+        let error = Erosion::from_lines(layered, &Rule::default()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_grid_n() {
+        let grid = GridN::from_lines(example_lines()).unwrap();
+        assert_eq!(grid.dims(), &[10, 10]);
+        assert_eq!(grid.accessible(&Rule::default()).unwrap(), 13);
+
+        // two copies of the example stacked: a roll with c neighbours in its
+        // layer now has 2c + 1, so only rolls with c <= 1 stay accessible
+        let stacked = EXAMPLE.iter().chain([""].iter()).chain(EXAMPLE.iter());
+        let cube = GridN::from_lines(stacked.map(|l| Ok(l.to_string()))).unwrap();
+        assert_eq!(cube.dims(), &[2, 10, 10]);
+        assert_eq!(cube.accessible(&Rule::default()).unwrap(), 2);
+
+        // peeling agrees with rescanning the 3-D grid after every wave
+        let rule = Rule {
+            threshold: 9,
+            ..Rule::default()
+        };
+        assert_eq!(rule.neighbourhood.offsets(3).unwrap().len(), 26);
+        let (expected, rescanned) = rescan_waves(&cube, &rule);
+        let mut peeled = cube.clone();
+        let mut waves = peeled.peel(&rule).unwrap();
+        waves.iter_mut().for_each(|wave| wave.sort());
+        assert!(expected.len() > 1);
+        assert_eq!(waves, expected);
+        assert_eq!(peeled, rescanned);
+
+        let ragged = ["@@", "", "@"].iter().map(|l| Ok(l.to_string()));
+        assert!(GridN::from_lines(ragged).is_err());
+    }
+
+    #[test]
+    fn test_hexagonal_needs_2d() {
+        let layers = ["@@", "", "@@"].iter().map(|l| Ok(l.to_string()));
+        let hex = Rule {
+            neighbourhood: Neighbourhood::Hexagonal,
+            ..Rule::default()
+        };
+        let error = solve04_with_rule(layers, &hex).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_layered_input() {
+        let solve = |input: &[&str]| solve04(input.iter().map(|l| Ok(l.to_string()))).unwrap();
+        // in 3-D the layers are neighbours: rolls in the outer columns see 4
+        // or 5 others, those in the middle column see all 7
+        assert_eq!(solve(&["@@.", ".@@", "", "@..", "@@@"]), 0);
+        // one row per layer: no roll has more than 3 neighbours
+        assert_eq!(solve(&["@@.", "", ".@@"]), 4);
+    }
 }
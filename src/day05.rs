@@ -1,4 +1,5 @@
-use std::io;
+use std::io::{self, Write};
+use std::{error, fmt};
 
 fn merge_intervals(intervals: &Vec<(i64, i64)>) -> Vec<(i64, i64)> {
     let mut merged: Vec<(i64, i64)> = Vec::new();
@@ -19,44 +20,342 @@ fn merge_intervals(intervals: &Vec<(i64, i64)>) -> Vec<(i64, i64)> {
     merged
 }
 
-fn is_in_intervals(n: i64, intervals: &Vec<(i64, i64)>) -> bool {
-    for interval in intervals {
-        if interval.0 <= n && interval.1 >= n {
-            return true;
+/// Sorted, disjoint ranges answering membership queries by binary search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeSet {
+    merged: Vec<(i64, i64)>,
+}
+
+impl RangeSet {
+    pub fn new(ranges: &[(i64, i64)]) -> Self {
+        let mut sorted = ranges.to_vec();
+        sorted.sort_by_key(|&(a, b)| (a, b));
+        RangeSet {
+            merged: merge_intervals(&sorted),
+        }
+    }
+
+    pub fn intervals(&self) -> &[(i64, i64)] {
+        &self.merged
+    }
+
+    /// Number of IDs covered, or `None` on overflow.
+    pub fn covered(&self) -> Option<i128> {
+        self.merged.iter().try_fold(0i128, |total, &(lo, hi)| {
+            total.checked_add((hi as i128 - lo as i128).checked_add(1)?)
+        })
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        // first interval ending at or after `id`
+        let k = self.merged.partition_point(|&(_, hi)| hi < id);
+        self.merged.get(k).is_some_and(|&(lo, _)| lo <= id)
+    }
+
+    /// Membership of every ID, in input order, by sorting the IDs and
+    /// sweeping them against the intervals once: O(q log q + n).
+    pub fn contains_all(&self, ids: &[i64]) -> Vec<bool> {
+        let mut order: Vec<usize> = (0..ids.len()).collect();
+        order.sort_unstable_by_key(|&k| ids[k]);
+        let mut fresh = vec![false; ids.len()];
+        let mut intervals = self.merged.iter().peekable();
+        for k in order {
+            while intervals.next_if(|&&(_, hi)| hi < ids[k]).is_some() {}
+            fresh[k] = intervals.peek().is_some_and(|&&(lo, _)| lo <= ids[k]);
         }
+        fresh
     }
-    false
 }
 
-pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut fresh = 0;
-    let mut intervals: Vec<(i64, i64)> = Vec::new();
-    for line in lines {
-        let line = line?;
-        if line.contains('-') {
-            let (left, right) = line.split_once("-").unwrap();
-            let left = left.parse::<i64>().unwrap();
-            let right = right.parse::<i64>().unwrap();
-
-            intervals.push((left, right));
-        } else if line.len() >= 1 {
-            let n = line.parse::<i64>().unwrap();
-            if is_in_intervals(n, &intervals) {
-                fresh += 1;
+/// How `answer_queries` matches IDs against the ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryMode {
+    /// Answer each line as it arrives, by binary search.
+    Stream,
+    /// Read every query first, then sort and sweep.
+    Batch,
+}
+
+/// Reads one ingredient ID per line from `queries` and writes `fresh` or
+/// `spoiled` for each, in order. Blank lines are skipped. Returns the number
+/// of fresh and spoiled IDs.
+pub fn answer_queries(
+    set: &RangeSet,
+    queries: impl Iterator<Item = io::Result<String>>,
+    mode: QueryMode,
+    out: &mut impl Write,
+) -> io::Result<(usize, usize)> {
+    let ids = queries.enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        let text = line.trim();
+        if text.is_empty() {
+            return None;
+        }
+        Some(text.parse::<i64>().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("query line {}: cannot parse {:?}", i + 1, text),
+            )
+        }))
+    });
+
+    let mut counts = (0, 0);
+    let mut answer = |fresh: bool| {
+        if fresh {
+            counts.0 += 1;
+            writeln!(out, "fresh")
+        } else {
+            counts.1 += 1;
+            writeln!(out, "spoiled")
+        }
+    };
+    match mode {
+        QueryMode::Stream => {
+            for id in ids {
+                answer(set.contains(id?))?;
+            }
+        }
+        QueryMode::Batch => {
+            let ids = ids.collect::<io::Result<Vec<i64>>>()?;
+            for fresh in set.contains_all(&ids) {
+                answer(fresh)?;
             }
         }
     }
+    Ok(counts)
+}
 
-    intervals.sort_by_key(|&(a, b)| (a, b));
-    let merged = merge_intervals(&intervals);
-    let mut part2 = 0;
-    for (i, interval) in merged.iter().enumerate() {
-        part2 += interval.1 - interval.0 + 1;
-        println!("Interval {} {:?}", i, interval);
+/// Problems in the inventory, located by 1-based line number.
+#[derive(Debug, PartialEq)]
+pub enum InventoryError {
+    /// An ingredient ID before the blank line.
+    IdInRangeSection {
+        line: usize,
+    },
+    /// A range after the blank line.
+    RangeInIdSection {
+        line: usize,
+    },
+    /// A range whose start is after its end.
+    Reversed {
+        line: usize,
+    },
+    Malformed {
+        line: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::IdInRangeSection { line } => {
+                write!(f, "line {}: ingredient ID in the range section", line)
+            }
+            InventoryError::RangeInIdSection { line } => {
+                write!(f, "line {}: range in the ingredient ID section", line)
+            }
+            InventoryError::Reversed { line } => {
+                write!(f, "line {}: range start is after its end", line)
+            }
+            InventoryError::Malformed { line, text } => {
+                write!(f, "line {}: cannot parse {:?}", line, text)
+            }
+        }
+    }
+}
+
+impl error::Error for InventoryError {}
+
+impl From<InventoryError> for io::Error {
+    fn from(e: InventoryError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
     }
-    println!("Part 2: {}", part2);
+}
+
+/// Parses "lo-hi"; either bound may be negative, so the separator is the
+/// first '-' after the first character.
+fn parse_range(text: &str) -> Option<(i64, i64)> {
+    let cut = text.get(1..)?.find('-')? + 1;
+    Some((text[..cut].parse().ok()?, text[cut + 1..].parse().ok()?))
+}
+
+/// The fresh ranges and the available ingredient IDs, in input order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    pub ranges: Vec<(i64, i64)>,
+    pub ids: Vec<i64>,
+}
 
-    Ok(fresh)
+impl Inventory {
+    /// Reads the ranges, a blank line, then the IDs. Every line is checked
+    /// against the section it appears in.
+    pub fn from_lines(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Self> {
+        let mut inventory = Inventory {
+            ranges: Vec::new(),
+            ids: Vec::new(),
+        };
+        let mut in_ids = false;
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let text = line.trim();
+            let n = i + 1;
+            if text.is_empty() {
+                // the first blank line ends the ranges, later ones are ignored
+                in_ids = true;
+                continue;
+            }
+            let range = parse_range(text);
+            let id = text.parse::<i64>().ok();
+            match (in_ids, range, id) {
+                (false, Some((lo, hi)), _) if lo > hi => {
+                    return Err(InventoryError::Reversed { line: n }.into())
+                }
+                (false, Some(range), _) => inventory.ranges.push(range),
+                (false, None, Some(_)) => {
+                    return Err(InventoryError::IdInRangeSection { line: n }.into())
+                }
+                (true, _, Some(id)) => inventory.ids.push(id),
+                (true, Some(_), None) => {
+                    return Err(InventoryError::RangeInIdSection { line: n }.into())
+                }
+                _ => {
+                    return Err(InventoryError::Malformed {
+                        line: n,
+                        text: text.to_string(),
+                    }
+                    .into())
+                }
+            }
+        }
+        Ok(inventory)
+    }
+}
+
+/// Statistics about the union of the fresh ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnionReport {
+    pub input_ranges: usize,
+    pub merged_ranges: usize,
+    /// Input ranges covered by the union of the others, with their indices.
+    pub redundant: Vec<(usize, (i64, i64))>,
+    /// Largest number of input ranges sharing an ID, 0 without ranges.
+    pub max_depth: usize,
+    /// First maximal run of IDs at `max_depth`.
+    pub max_depth_at: Option<(i64, i64)>,
+    pub covered: i128,
+}
+
+impl UnionReport {
+    /// Sweeps the range boundaries once to get the depth of every run of IDs
+    /// between consecutive boundaries. A range is redundant when none of the
+    /// runs it spans has depth 1, i.e. it is never the only range.
+    pub fn new(ranges: &[(i64, i64)]) -> io::Result<Self> {
+        let set = RangeSet::new(ranges);
+        let covered = set.covered().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "covered ID count overflows")
+        })?;
+
+        // a range [lo, hi] adds 1 from lo and removes it after hi
+        let mut events: Vec<(i128, i64)> = ranges
+            .iter()
+            .flat_map(|&(lo, hi)| [(lo as i128, 1), (hi as i128 + 1, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut bounds: Vec<i128> = Vec::new();
+        let mut depths: Vec<i64> = Vec::new();
+        for (x, delta) in events {
+            if bounds.last() != Some(&x) {
+                bounds.push(x);
+                depths.push(depths.last().copied().unwrap_or(0));
+            }
+            *depths.last_mut().unwrap() += delta;
+        }
+
+        // singles[k]: runs of depth 1 among the first k
+        let mut singles = vec![0usize; depths.len() + 1];
+        for (k, &depth) in depths.iter().enumerate() {
+            singles[k + 1] = singles[k] + (depth == 1) as usize;
+        }
+        let run = |x: i128| bounds.binary_search(&x).unwrap();
+        let redundant = ranges
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(lo, hi))| singles[run(hi as i128 + 1)] == singles[run(lo as i128)])
+            .map(|(i, &range)| (i, range))
+            .collect();
+
+        let max_depth = depths.iter().copied().max().unwrap_or(0);
+        let max_depth_at = depths
+            .iter()
+            .position(|&depth| depth == max_depth && depth > 0)
+            .map(|k| (bounds[k] as i64, (bounds[k + 1] - 1) as i64));
+
+        Ok(UnionReport {
+            input_ranges: ranges.len(),
+            merged_ranges: set.intervals().len(),
+            redundant,
+            max_depth: max_depth as usize,
+            max_depth_at,
+            covered,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let redundant: Vec<String> = self
+            .redundant
+            .iter()
+            .map(|&(i, (lo, hi))| format!("{{\"index\":{},\"lo\":{},\"hi\":{}}}", i, lo, hi))
+            .collect();
+        let max_depth_at = match self.max_depth_at {
+            Some((lo, hi)) => format!("{{\"lo\":{},\"hi\":{}}}", lo, hi),
+            None => String::from("null"),
+        };
+        format!(
+            "{{\"input_ranges\":{},\"merged_ranges\":{},\"redundant\":[{}],\"max_depth\":{},\"max_depth_at\":{},\"covered\":{}}}",
+            self.input_ranges,
+            self.merged_ranges,
+            redundant.join(","),
+            self.max_depth,
+            max_depth_at,
+            self.covered
+        )
+    }
+}
+
+impl fmt::Display for UnionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Ranges: {} input, {} after merge",
+            self.input_ranges, self.merged_ranges
+        )?;
+        write!(f, "Redundant ranges:")?;
+        for (i, (lo, hi)) in &self.redundant {
+            write!(f, " #{} {}-{}", i, lo, hi)?;
+        }
+        writeln!(f)?;
+        match self.max_depth_at {
+            Some((lo, hi)) => writeln!(f, "Max overlap: {} at {}-{}", self.max_depth, lo, hi)?,
+            None => writeln!(f, "Max overlap: 0")?,
+        }
+        writeln!(f, "Covered IDs: {}", self.covered)
+    }
+}
+
+pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    let inventory = Inventory::from_lines(lines)?;
+    let set = RangeSet::new(&inventory.ranges);
+    let fresh = inventory.ids.iter().filter(|&&id| set.contains(id)).count();
+
+    let report = UnionReport::new(&inventory.ranges)?;
+    print!("{}", report);
+    println!("Part 2: {}", report.covered);
+
+    Ok(fresh as i64)
 }
 
 #[cfg(test)]
@@ -74,6 +373,7 @@ mod tests {
             Ok(String::from("10-14")),
             Ok(String::from("16-20")),
             Ok(String::from("12-18")),
+            Ok(String::new()),
             Ok(String::from("1")),
             Ok(String::from("5")),
             Ok(String::from("8")),
@@ -85,4 +385,98 @@ mod tests {
         let result = solve(input.into_iter()).unwrap();
         assert_eq!(result, 3);
     }
+
+    fn lines(input: &[&str]) -> impl Iterator<Item = io::Result<String>> {
+        input
+            .iter()
+            .map(|l| Ok(l.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_negative_ids_and_sections() {
+        let inventory =
+            Inventory::from_lines(lines(&["-10--5", "-2-3", "7-9", "", "-7", "-3", "0", "8"]))
+                .unwrap();
+        assert_eq!(inventory.ranges, vec![(-10, -5), (-2, 3), (7, 9)]);
+        let set = RangeSet::new(&inventory.ranges);
+        let fresh: Vec<bool> = inventory.ids.iter().map(|&id| set.contains(id)).collect();
+        assert_eq!(fresh, vec![true, false, true, true]);
+
+        let error = |input: &[&str]| {
+            Inventory::from_lines(lines(input))
+                .unwrap_err()
+                .into_inner()
+                .unwrap()
+                .downcast::<InventoryError>()
+                .map(|e| *e)
+                .unwrap()
+        };
+        assert_eq!(
+            error(&["3-5", "4", "", "1"]),
+            InventoryError::IdInRangeSection { line: 2 }
+        );
+        assert_eq!(
+            error(&["3-5", "", "1", "10-14"]),
+            InventoryError::RangeInIdSection { line: 4 }
+        );
+        assert_eq!(error(&["5-3"]), InventoryError::Reversed { line: 1 });
+        assert_eq!(
+            error(&["3-5", "", "x"]),
+            InventoryError::Malformed {
+                line: 3,
+                text: String::from("x")
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_modes() {
+        let set = RangeSet::new(&[(3, 5), (10, 14), (16, 20), (12, 18)]);
+        let queries = ["1", "5", "", "8", "11", "17", "32", "-4", "20"];
+        for mode in [QueryMode::Stream, QueryMode::Batch] {
+            let mut out = Vec::new();
+            let counts = answer_queries(&set, lines(&queries), mode, &mut out).unwrap();
+            assert_eq!(counts, (4, 4));
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                "spoiled\nfresh\nspoiled\nfresh\nfresh\nspoiled\nspoiled\nfresh\n"
+            );
+        }
+        assert!(
+            answer_queries(&set, lines(&["7", "x"]), QueryMode::Batch, &mut io::sink()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_union_report() {
+        let ranges = [(3, 5), (10, 14), (16, 20), (12, 18), (11, 13), (4, 4)];
+        let report = UnionReport::new(&ranges).unwrap();
+        assert_eq!(report.input_ranges, 6);
+        assert_eq!(report.merged_ranges, 2);
+        assert_eq!(report.redundant, vec![(4, (11, 13)), (5, (4, 4))]);
+        assert_eq!(report.max_depth, 3);
+        assert_eq!(report.max_depth_at, Some((12, 13)));
+        assert_eq!(report.covered, 14);
+        assert!(report
+            .to_string()
+            .contains("Redundant ranges: #4 11-13 #5 4-4\n"));
+        assert_eq!(
+            report.to_json(),
+            "{\"input_ranges\":6,\"merged_ranges\":2,\"redundant\":[{\"index\":4,\"lo\":11,\"hi\":13},{\"index\":5,\"lo\":4,\"hi\":4}],\"max_depth\":3,\"max_depth_at\":{\"lo\":12,\"hi\":13},\"covered\":14}"
+        );
+
+        // the whole i64 line does not fit in an i64 count
+        let full = UnionReport::new(&[(i64::MIN, 0), (1, i64::MAX), (i64::MAX, i64::MAX)]).unwrap();
+        assert_eq!(full.covered, 1i128 << 64);
+        assert_eq!(full.redundant, vec![(2, (i64::MAX, i64::MAX))]);
+        assert_eq!(full.max_depth_at, Some((i64::MAX, i64::MAX)));
+
+        let empty = UnionReport::new(&[]).unwrap();
+        assert_eq!(
+            (empty.max_depth, empty.max_depth_at, empty.covered),
+            (0, None, 0)
+        );
+    }
 }
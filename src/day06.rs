@@ -1,86 +1,339 @@
-use std::io;
+use std::ops::Range;
+use std::{error, fmt, io};
 
-#[derive(Debug, Copy, Clone)]
-enum Op {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Op {
     Add,
     Prod,
 }
 
-fn part2(matrix: &Vec<Vec<char>>) -> i64 {
-    let rows = matrix.len();
-    let cols = matrix[0].len();
-    let mut res: i64 = 0;
-    let mut op: Op = Op::Add;
-    let mut block_res: i64 = 0;
-    for col in 0..cols {
-        if matrix[rows - 1][col] == '*' {
-            res += block_res;
-            // println!("partial res: {}", block_res);
-            block_res = 1;
-            op = Op::Prod;
-        } else if matrix[rows - 1][col] == '+' {
-            res += block_res;
-            // println!("partial res: {}", block_res);
-            block_res = 0;
-            op = Op::Add;
+/// Non-negative integer type a worksheet can be evaluated in.
+pub trait Number: Sized + fmt::Display {
+    fn from_digit(d: u32) -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+impl Number for i64 {
+    fn from_digit(d: u32) -> Self {
+        d as i64
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i64::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i64::checked_mul(*self, *other)
+    }
+}
+
+impl Number for i128 {
+    fn from_digit(d: u32) -> Self {
+        d as i128
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i128::checked_mul(*self, *other)
+    }
+}
+
+const BIG_BASE: u64 = 1_000_000_000;
+
+/// Arbitrary-precision non-negative integer, stored as little-endian limbs
+/// in base 10^9 so that printing needs no division. Never overflows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigUint(Vec<u32>);
+
+impl BigUint {
+    fn trim(mut self) -> Self {
+        while self.0.len() > 1 && self.0.last() == Some(&0) {
+            self.0.pop();
         }
-        let number: i64 = matrix
-            .iter()
-            .take(rows - 1) // skip last row
-            .map(|row| row[col])
-            .filter_map(|c| c.to_digit(10)) // char → digit
-            .fold(0_i64, |acc, d| acc * 10 + d as i64);
-        if number == 0 {
-            continue;
+        self
+    }
+}
+
+impl Number for BigUint {
+    fn from_digit(d: u32) -> Self {
+        BigUint(vec![d])
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        let mut limbs = Vec::with_capacity(self.0.len().max(other.0.len()) + 1);
+        let mut carry = 0;
+        for k in 0..self.0.len().max(other.0.len()) {
+            let sum = carry
+                + self.0.get(k).copied().unwrap_or(0) as u64
+                + other.0.get(k).copied().unwrap_or(0) as u64;
+            limbs.push((sum % BIG_BASE) as u32);
+            carry = sum / BIG_BASE;
         }
+        limbs.push(carry as u32);
+        Some(BigUint(limbs).trim())
+    }
 
-        block_res = match op {
-            Op::Add => block_res + number,
-            Op::Prod => block_res * number,
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let mut limbs = vec![0u64; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.0.iter().enumerate() {
+                let cur = limbs[i + j] + a as u64 * b as u64 + carry;
+                limbs[i + j] = cur % BIG_BASE;
+                carry = cur / BIG_BASE;
+            }
+            limbs[i + other.0.len()] += carry;
         }
+        Some(BigUint(limbs.into_iter().map(|l| l as u32).collect()).trim())
     }
-    res += block_res;
-    // println!("partial res: {}", block_res);
-    res
 }
 
-pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut matrix: Vec<Vec<i64>> = Vec::new();
-    let mut ans = 0;
-    let mut matrix2: Vec<Vec<char>> = Vec::new();
-
-    for raw_line in lines {
-        let line_str = raw_line?;
-        // collect all chars for part2
-        matrix2.push(line_str.chars().collect());
-
-        let line = line_str.trim();
-        if line.is_empty() {
-            continue;
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut limbs = self.0.iter().rev();
+        write!(f, "{}", limbs.next().unwrap_or(&0))?;
+        for limb in limbs {
+            write!(f, "{:09}", limb)?;
         }
+        Ok(())
+    }
+}
 
-        let tokens: Vec<&str> = line.split_whitespace().collect();
-
-        // If every token is an int -> matrix row
-        if tokens.iter().all(|t| t.parse::<i64>().is_ok()) {
-            let row: Vec<i64> = tokens.iter().map(|t| t.parse::<i64>().unwrap()).collect();
-            matrix.push(row);
-        } else {
-            for (i, op) in tokens.iter().enumerate() {
-                let col: Vec<i64> = matrix.iter().map(|row| row[i]).collect();
-                let s: i64 = match *op {
-                    "+" => col.iter().sum(),
-                    "*" => col.iter().product(),
-                    _ => 0, // or return Err(...)
-                };
-                ans += s;
+/// Which step of the worksheet, and under which reading, did not fit the
+/// chosen number type.
+#[derive(Debug, PartialEq)]
+pub enum OverflowError {
+    /// 1-based problem number, counting from the left.
+    Problem(Reading, usize),
+    GrandTotal(Reading),
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverflowError::Problem(reading, k) => {
+                write!(f, "{}: problem {} overflows", reading, k)
+            }
+            OverflowError::GrandTotal(reading) => {
+                write!(f, "{}: the grand total overflows", reading)
             }
         }
     }
+}
+
+impl error::Error for OverflowError {}
+
+impl From<OverflowError> for io::Error {
+    fn from(e: OverflowError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Number type used to evaluate the worksheet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    I64,
+    I128,
+    Big,
+}
+
+/// Parses a decimal digit string, `None` on overflow.
+fn parse_number<N: Number>(digits: &str) -> Option<N> {
+    digits.chars().try_fold(N::from_digit(0), |acc, c| {
+        acc.checked_mul(&N::from_digit(10))?
+            .checked_add(&N::from_digit(c.to_digit(10)?))
+    })
+}
+
+fn apply<N: Number>(op: Op, acc: &N, number: &N) -> Option<N> {
+    match op {
+        Op::Add => acc.checked_add(number),
+        Op::Prod => acc.checked_mul(number),
+    }
+}
+
+fn identity<N: Number>(op: Op) -> N {
+    match op {
+        Op::Add => N::from_digit(0),
+        Op::Prod => N::from_digit(1),
+    }
+}
 
-    println!("part1: {}", ans);
-    let ans2 = part2(&matrix2);
-    Ok(ans2)
+/// One problem of the worksheet: its operands, as decimal digit strings so
+/// that any `Number` type can evaluate them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub operator: Op,
+    pub operands: Vec<String>,
+}
+
+/// How the numbers of a problem are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reading {
+    /// Part 1: each row of the problem is a number.
+    Horizontal,
+    /// Part 2: each column is a number, most significant digit on top, and
+    /// the columns are read right to left.
+    Columnar,
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reading::Horizontal => write!(f, "part 1 (horizontal)"),
+            Reading::Columnar => write!(f, "part 2 (columnar)"),
+        }
+    }
+}
+
+/// The worksheet as a character grid padded to a rectangle, with the column
+/// range of each problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Worksheet {
+    grid: Vec<Vec<char>>,
+    problems: Vec<Range<usize>>,
+}
+
+impl Worksheet {
+    /// Reads the number rows and the operator row (the last non-blank line),
+    /// and splits the problems on columns that are blank in every row.
+    pub fn from_lines(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Self> {
+        let mut grid: Vec<Vec<char>> = Vec::new();
+        for line in lines {
+            let line = line?;
+            if !line.trim().is_empty() {
+                grid.push(line.trim_end().chars().collect());
+            }
+        }
+        let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in grid.iter_mut() {
+            row.resize(width, ' ');
+        }
+
+        let mut problems = Vec::new();
+        let mut start = None;
+        for col in 0..=width {
+            let blank = col == width || grid.iter().all(|row| row[col] == ' ');
+            match (start, blank) {
+                (None, false) => start = Some(col),
+                (Some(s), true) => {
+                    problems.push(s..col);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        Ok(Worksheet { grid, problems })
+    }
+
+    /// The problems, left to right, under the given reading.
+    pub fn problems(&self, reading: Reading) -> io::Result<Vec<Problem>> {
+        let Some((ops, numbers)) = self.grid.split_last() else {
+            return Ok(Vec::new());
+        };
+        self.problems
+            .iter()
+            .enumerate()
+            .map(|(k, cols)| {
+                let operator = match ops[cols.clone()].iter().find(|&&c| c != ' ') {
+                    Some('+') => Op::Add,
+                    Some('*') => Op::Prod,
+                    found => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("problem {}: bad operator {:?}", k + 1, found),
+                        ))
+                    }
+                };
+                let operands: Vec<String> = match reading {
+                    Reading::Horizontal => numbers
+                        .iter()
+                        .map(|row| row[cols.clone()].iter().filter(|&&c| c != ' ').collect())
+                        .collect(),
+                    Reading::Columnar => cols
+                        .clone()
+                        .rev()
+                        .map(|col| {
+                            numbers
+                                .iter()
+                                .map(|row| row[col])
+                                .filter(|&c| c != ' ')
+                                .collect()
+                        })
+                        .collect(),
+                };
+                let operands: Vec<String> =
+                    operands.into_iter().filter(|o| !o.is_empty()).collect();
+                if let Some(bad) = operands
+                    .iter()
+                    .find(|o| !o.chars().all(|c| c.is_ascii_digit()))
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("problem {}: bad operand {:?}", k + 1, bad),
+                    ));
+                }
+                Ok(Problem { operator, operands })
+            })
+            .collect()
+    }
+}
+
+/// Grand total of `problems`, with checked arithmetic in `N`; `reading` only
+/// labels the overflow error.
+fn evaluate<N: Number>(problems: &[Problem], reading: Reading) -> Result<N, OverflowError> {
+    problems
+        .iter()
+        .enumerate()
+        .try_fold(N::from_digit(0), |total, (k, problem)| {
+            let overflow = || OverflowError::Problem(reading, k + 1);
+            let value = problem
+                .operands
+                .iter()
+                .try_fold(identity(problem.operator), |acc: N, operand| {
+                    apply(problem.operator, &acc, &parse_number(operand)?)
+                })
+                .ok_or_else(overflow)?;
+            total
+                .checked_add(&value)
+                .ok_or(OverflowError::GrandTotal(reading))
+        })
+}
+
+fn solve_in<N: Number>(worksheet: &Worksheet) -> io::Result<String> {
+    let part1: N = evaluate(
+        &worksheet.problems(Reading::Horizontal)?,
+        Reading::Horizontal,
+    )?;
+    println!("part1: {}", part1);
+    let part2: N = evaluate(&worksheet.problems(Reading::Columnar)?, Reading::Columnar)?;
+    Ok(part2.to_string())
+}
+
+/// Solves both parts in the given precision, returning the part 2 grand
+/// total as a decimal string. Overflow is reported with the part and the
+/// problem number.
+pub fn solve_with_precision(
+    lines: impl Iterator<Item = io::Result<String>>,
+    precision: Precision,
+) -> io::Result<String> {
+    let worksheet = Worksheet::from_lines(lines)?;
+    match precision {
+        Precision::I64 => solve_in::<i64>(&worksheet),
+        Precision::I128 => solve_in::<i128>(&worksheet),
+        Precision::Big => solve_in::<BigUint>(&worksheet),
+    }
+}
+
+pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    solve_with_precision(lines, Precision::I64)?
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
 #[cfg(test)]
@@ -99,4 +352,104 @@ mod tests {
         let result = solve(input.into_iter()).unwrap();
         assert_eq!(result, 3263827);
     }
+
+    #[test]
+    fn test_overflow_and_precision() {
+        // part 1 multiplies 10^10 by 10^10 in problem 2
+        let input = ["1 10000000000", "2 10000000000", "+ *            "];
+        let lines = || input.iter().map(|l| Ok(l.to_string()));
+        let error = solve_with_precision(lines(), Precision::I64).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "part 1 (horizontal): problem 2 overflows"
+        );
+        // part 2 reads 12 + (11 * 0 * ... * 0)
+        assert_eq!(
+            solve_with_precision(lines(), Precision::I128).unwrap(),
+            "12"
+        );
+        let nines = ["9".repeat(20), String::from("*")];
+        let worksheet = Worksheet::from_lines(nines.into_iter().map(Ok)).unwrap();
+        let columns = worksheet.problems(Reading::Columnar).unwrap();
+        assert_eq!(
+            evaluate::<i64>(&columns, Reading::Columnar),
+            Err(OverflowError::Problem(Reading::Columnar, 1))
+        );
+        assert_eq!(
+            evaluate::<i128>(&columns, Reading::Columnar),
+            Ok(9i128.pow(20))
+        );
+
+        let wide = ["99999999999999999999", "99999999999999999999", "*"];
+        let lines = || wide.iter().map(|l| Ok(l.to_string()));
+        let error = solve_with_precision(lines(), Precision::I128).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "part 1 (horizontal): problem 1 overflows"
+        );
+        // part 2 multiplies twenty columns reading 99
+        assert_eq!(
+            solve_with_precision(lines(), Precision::Big).unwrap(),
+            "8179069375972308708891986605443361898001"
+        );
+    }
+
+    #[test]
+    fn test_big_uint() {
+        let big = |n: u128| parse_number::<BigUint>(&n.to_string()).unwrap();
+        let values = [
+            0u128,
+            1,
+            999_999_999,
+            1_000_000_000,
+            123_456_789_012_345_678,
+        ];
+        for &a in &values {
+            for &b in &values {
+                assert_eq!(big(a).checked_add(&big(b)).unwrap(), big(a + b));
+                assert_eq!(big(a).checked_mul(&big(b)).unwrap(), big(a * b));
+            }
+        }
+        let max = big(u128::MAX);
+        assert_eq!(max.to_string(), u128::MAX.to_string());
+        assert_eq!(
+            max.checked_mul(&max).unwrap().to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+    }
+
+    #[test]
+    fn test_worksheet_readings() {
+        let input = [
+            "123 328  51 64 ",
+            " 45 64  387 23 ",
+            "  6 98  215 314",
+            "*   +   *   +  ",
+        ];
+        let worksheet = Worksheet::from_lines(input.iter().map(|l| Ok(l.to_string()))).unwrap();
+        let problem = |operator, operands: &[&str]| Problem {
+            operator,
+            operands: operands.iter().map(|o| o.to_string()).collect(),
+        };
+
+        let rows = worksheet.problems(Reading::Horizontal).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], problem(Op::Prod, &["123", "45", "6"]));
+        assert_eq!(rows[3], problem(Op::Add, &["64", "23", "314"]));
+        assert_eq!(evaluate::<i64>(&rows, Reading::Horizontal), Ok(4277556));
+
+        let columns = worksheet.problems(Reading::Columnar).unwrap();
+        assert_eq!(columns[0], problem(Op::Prod, &["356", "24", "1"]));
+        assert_eq!(columns[3], problem(Op::Add, &["4", "431", "623"]));
+        assert_eq!(evaluate::<i64>(&columns, Reading::Columnar), Ok(3263827));
+
+        let bad = ["12 3", "*  -"].iter().map(|l| Ok(l.to_string()));
+        let error = Worksheet::from_lines(bad)
+            .unwrap()
+            .problems(Reading::Horizontal);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "problem 2: bad operator Some('-')"
+        );
+    }
 }
@@ -1,11 +1,12 @@
 use itertools::Itertools;
-use std::collections::BTreeSet;
-use std::{fmt, io};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::{error, fmt, io};
 
-#[derive(Debug)]
-struct Point2D {
-    x: i64,
-    y: i64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point2D {
+    pub x: i64,
+    pub y: i64,
 }
 
 // Implement Display for Point2D
@@ -20,7 +21,7 @@ impl Point2D {
     fn from_string(s: &str) -> Result<Self, &'static str> {
         let coords: Vec<&str> = s.split(',').collect();
         if coords.len() != 2 {
-            return Err("Input string must be in the format 'x,y,z'");
+            return Err("Input string must be in the format 'x,y'");
         }
         let x = coords[0]
             .trim()
@@ -34,23 +35,151 @@ impl Point2D {
     }
 }
 
-pub fn solve_part_1(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let points: Vec<Point2D> = lines
-        .filter_map(|line| {
-            // Filter out any lines that fail to read
-            let line = line.ok()?;
-            // Parse the line into a Point3D
-            Point2D::from_string(&line).ok()
-        })
-        .collect();
+/// How overlapping loops combine into the allowed region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside when a ray from the point crosses an odd number of edges,
+    /// so any loop nested in another one cuts a hole.
+    EvenOdd,
+    /// Inside when the loops wind around the point a non-zero number of times,
+    /// so a nested loop is a hole only if it runs the opposite way.
+    NonZero,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i64) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
 
-    let areas: Vec<i64> = points
+/// Red-tile loops and the fill rule deciding which points they enclose.
+#[derive(Debug, Clone)]
+pub struct Region {
+    loops: Vec<Vec<Point2D>>,
+    // 1-based source line of every vertex, for error messages
+    lines: Vec<Vec<usize>>,
+    rule: FillRule,
+}
+
+impl Region {
+    /// Numbers the vertices as `from_lines` would read them back: one per
+    /// line, with a blank line between loops.
+    pub fn new(loops: Vec<Vec<Point2D>>, rule: FillRule) -> Self {
+        let mut next = 1;
+        let lines = loops
+            .iter()
+            .map(|l| {
+                let numbers: Vec<usize> = (next..next + l.len()).collect();
+                next += l.len() + 1;
+                numbers
+            })
+            .collect();
+        Region { loops, lines, rule }
+    }
+
+    /// Reads loops of red tiles, one tile per line, separated by blank lines.
+    pub fn from_lines(
+        lines: impl Iterator<Item = io::Result<String>>,
+        rule: FillRule,
+    ) -> io::Result<Self> {
+        let mut loops: Vec<Vec<Point2D>> = vec![Vec::new()];
+        let mut numbers: Vec<Vec<usize>> = vec![Vec::new()];
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                if !loops.last().unwrap().is_empty() {
+                    loops.push(Vec::new());
+                    numbers.push(Vec::new());
+                }
+                continue;
+            }
+            let p = Point2D::from_string(&line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+            })?;
+            loops.last_mut().unwrap().push(p);
+            numbers.last_mut().unwrap().push(i + 1);
+        }
+        loops.retain(|l| !l.is_empty());
+        numbers.retain(|l| !l.is_empty());
+        // keep the real line numbers rather than the ones `new` derives
+        let mut region = Region::new(loops, rule);
+        region.lines = numbers;
+        Ok(region)
+    }
+
+    /// Checks every loop with `validate_polygon`.
+    pub fn validate(&self) -> io::Result<()> {
+        for (l, numbers) in self.loops.iter().zip(&self.lines) {
+            validate_polygon(l, numbers).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("loop from line {}: {}", numbers[0], e),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn points(&self) -> impl Iterator<Item = &Point2D> {
+        self.loops.iter().flatten()
+    }
+
+    /// Every directed edge of every loop.
+    fn edges(&self) -> impl Iterator<Item = (Point2D, Point2D)> + '_ {
+        self.loops
+            .iter()
+            .flat_map(|l| (0..l.len()).map(move |i| (l[i], l[(i + 1) % l.len()])))
+    }
+}
+
+/// Rectangle spanned by two red tiles at opposite corners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rectangle {
+    pub a: Point2D,
+    pub b: Point2D,
+    /// Inclusive area in tiles.
+    pub area: i64,
+}
+
+impl Rectangle {
+    pub fn new(a: Point2D, b: Point2D) -> Self {
+        Rectangle {
+            a,
+            b,
+            area: rect_area_tiles(&a, &b),
+        }
+    }
+}
+
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}: {}", self.a, self.b, self.area)
+    }
+}
+
+fn best_rectangle_part_1(points: &[Point2D]) -> Option<Rectangle> {
+    points
         .iter()
         .tuple_combinations()
-        .map(|(a, b)| ((a.x - b.x).abs() + 1) * ((a.y - b.y).abs() + 1))
-        .collect();
+        .map(|(a, b)| Rectangle::new(*a, *b))
+        .max_by_key(|r| r.area)
+}
+
+/// Part 1 witness: the largest rectangle with red tiles at two opposite
+/// corners, wherever it lies.
+pub fn witness_part_1(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Rectangle> {
+    let region = Region::from_lines(lines, FillRule::EvenOdd)?;
+    let points: Vec<Point2D> = region.points().copied().collect();
 
-    Ok(*areas.iter().max().unwrap())
+    best_rectangle_part_1(&points)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "need at least two red tiles"))
+}
+
+pub fn solve_part_1(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    Ok(witness_part_1(lines)?.area)
 }
 
 /// Inclusive rectangle area in "tiles" (matches your examples):
@@ -61,61 +190,312 @@ fn rect_area_tiles(a: &Point2D, b: &Point2D) -> i64 {
     w * h
 }
 
-/// Standard even-odd point-in-polygon test for an orthogonal polygon.
-/// Works on a point p in continuous plane. Boundary is treated as inside.
-/// Polygon vertices must form a simple closed loop in order.
-fn point_in_poly_even_odd(p: (f64, f64), poly: &[Point2D]) -> bool {
-    let (px, py) = p;
+/// Reasons a list of red tiles is not a simple orthogonal loop.
+/// Vertices and edges are given by the 1-based input line of the vertex
+/// (the edge's starting vertex for edges).
+#[derive(Debug, PartialEq)]
+pub enum PolygonError {
+    TooFewVertices(usize),
+    /// The same tile appears on both lines.
+    RepeatedVertex {
+        first: usize,
+        second: usize,
+    },
+    /// The edge starting on this line is neither horizontal nor vertical.
+    DiagonalEdge {
+        line: usize,
+    },
+    /// The loop doubles back on itself at the vertex on this line.
+    Spur {
+        line: usize,
+    },
+    /// The edges starting on these lines touch or cross.
+    SelfIntersection {
+        first: usize,
+        second: usize,
+    },
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(n) => {
+                write!(f, "polygon needs at least 4 vertices, got {}", n)
+            }
+            PolygonError::RepeatedVertex { first, second } => {
+                write!(f, "vertex on line {} repeats line {}", second, first)
+            }
+            PolygonError::DiagonalEdge { line } => {
+                write!(f, "edge from line {} is not axis-aligned", line)
+            }
+            PolygonError::Spur { line } => {
+                write!(f, "edges meeting at line {} fold back on each other", line)
+            }
+            PolygonError::SelfIntersection { first, second } => {
+                write!(f, "edges from lines {} and {} intersect", first, second)
+            }
+        }
+    }
+}
+
+impl error::Error for PolygonError {}
+
+/// Checks that `poly` is a simple closed loop of axis-aligned edges, reporting
+/// vertices by their source line in `lines`.
+fn validate_polygon(poly: &[Point2D], lines: &[usize]) -> Result<(), PolygonError> {
+    let n = poly.len();
+    if n < 4 {
+        return Err(PolygonError::TooFewVertices(n));
+    }
+
+    let mut seen: HashMap<Point2D, usize> = HashMap::new();
+    for (i, p) in poly.iter().enumerate() {
+        if let Some(&first) = seen.get(p) {
+            return Err(PolygonError::RepeatedVertex {
+                first: lines[first],
+                second: lines[i],
+            });
+        }
+        seen.insert(*p, i);
+    }
+
+    for i in 0..n {
+        let a = poly[i];
+        let b = poly[(i + 1) % n];
+        if a.x != b.x && a.y != b.y {
+            return Err(PolygonError::DiagonalEdge { line: lines[i] });
+        }
+    }
+
+    for i in 0..n {
+        let prev = poly[(i + n - 1) % n];
+        let cur = poly[i];
+        let next = poly[(i + 1) % n];
+        // both edges on the same line with prev and next on the same side of
+        // cur; compared by sign, as differences of large coordinates overflow
+        let back = if prev.y == cur.y && cur.y == next.y {
+            (prev.x < cur.x) == (next.x < cur.x)
+        } else if prev.x == cur.x && cur.x == next.x {
+            (prev.y < cur.y) == (next.y < cur.y)
+        } else {
+            false
+        };
+        if back {
+            return Err(PolygonError::Spur { line: lines[i] });
+        }
+    }
+
+    if let Some((first, second)) = touching_edges(poly) {
+        return Err(PolygonError::SelfIntersection {
+            first: lines[first],
+            second: lines[second],
+        });
+    }
+
+    Ok(())
+}
+
+/// First pair of non-adjacent edges sharing a point, found in O(n log n):
+/// collinear edges are compared along each line, then a sweep over x matches
+/// every vertical edge against the horizontal edges spanning its x.
+fn touching_edges(poly: &[Point2D]) -> Option<(usize, usize)> {
     let n = poly.len();
-    let mut inside = false;
+    let adjacent = |i: usize, j: usize| (i + 1) % n == j || (j + 1) % n == i;
+    let pair = |i: usize, j: usize| (i.min(j), i.max(j));
 
+    // (line, lo, hi, edge index)
+    let mut horizontal: Vec<(i64, i64, i64, usize)> = Vec::new();
+    let mut vertical: Vec<(i64, i64, i64, usize)> = Vec::new();
     for i in 0..n {
-        let a = &poly[i];
-        let b = &poly[(i + 1) % n];
+        let (a, b) = (poly[i], poly[(i + 1) % n]);
+        if a.y == b.y {
+            horizontal.push((a.y, a.x.min(b.x), a.x.max(b.x), i));
+        } else {
+            vertical.push((a.x, a.y.min(b.y), a.y.max(b.y), i));
+        }
+    }
 
-        let (x1, y1) = (a.x as f64, a.y as f64);
-        let (x2, y2) = (b.x as f64, b.y as f64);
+    // edges on one line may only meet at the vertex they share; `reach` is
+    // the edge reaching farthest along the line so far
+    for edges in [&mut horizontal, &mut vertical] {
+        edges.sort_unstable();
+        let mut reach: Option<(i64, i64, usize)> = None;
+        for &(line, lo, hi, i) in edges.iter() {
+            if let Some((r_line, r_hi, j)) = reach {
+                if r_line == line && (lo < r_hi || (lo == r_hi && !adjacent(i, j))) {
+                    return Some(pair(i, j));
+                }
+            }
+            if reach.is_none_or(|(r_line, r_hi, _)| r_line != line || hi > r_hi) {
+                reach = Some((line, hi, i));
+            }
+        }
+    }
+
+    // horizontal edges are active over their closed x range: at equal x they
+    // are opened (0) before the vertical edges are queried (1), closed (2) after
+    let mut events: Vec<(i64, u8, usize)> = Vec::new();
+    for (k, &(_, lo, hi, _)) in horizontal.iter().enumerate() {
+        events.push((lo, 0, k));
+        events.push((hi, 2, k));
+    }
+    for (k, &(x, _, _, _)) in vertical.iter().enumerate() {
+        events.push((x, 1, k));
+    }
+    events.sort_unstable();
 
-        // Boundary check for axis-aligned edges
-        if y1 == y2 {
-            // horizontal
-            if (py == y1) && (px >= x1.min(x2)) && (px <= x1.max(x2)) {
-                return true;
+    // (y, edge index) of the active horizontal edges
+    let mut active = BTreeSet::<(i64, usize)>::new();
+    for (_, kind, k) in events {
+        match kind {
+            0 => {
+                let (y, _, _, h) = horizontal[k];
+                active.insert((y, h));
             }
-        } else if x1 == x2 {
-            // vertical
-            if (px == x1) && (py >= y1.min(y2)) && (py <= y1.max(y2)) {
-                return true;
+            2 => {
+                let (y, _, _, h) = horizontal[k];
+                active.remove(&(y, h));
+            }
+            _ => {
+                let (_, lo, hi, i) = vertical[k];
+                // at most two active edges are adjacent to this one
+                let hit = active
+                    .range((lo, 0)..=(hi, usize::MAX))
+                    .find(|&&(_, j)| !adjacent(i, j));
+                if let Some(&(_, j)) = hit {
+                    return Some(pair(i, j));
+                }
             }
         }
+    }
 
-        // Ray cast to +inf in x direction: count crossings
-        // Consider edges that straddle py
+    None
+}
+
+/// Winding number of the loops around `p`, with the loop coordinates scaled
+/// by `scale` in `i128`. `p` must not lie on any edge.
+fn winding(p: (i128, i128), region: &Region, scale: i128) -> i64 {
+    let (px, py) = p;
+    let mut winding: i64 = 0;
+    for (a, b) in region.edges() {
+        let x1 = scale * a.x as i128;
+        let (y1, y2) = (scale * a.y as i128, scale * b.y as i128);
+
+        // Ray cast to +inf in x direction: only vertical edges straddling py count,
+        // signed by direction so the sum is the winding number
         let cond = (y1 > py) != (y2 > py);
-        if cond {
-            let x_int = x1 + (py - y1) * (x2 - x1) / (y2 - y1);
-            if x_int > px {
-                inside = !inside;
-            }
+        if cond && x1 > px {
+            winding += if y2 > y1 { 1 } else { -1 };
         }
     }
+    winding
+}
 
-    inside
+/// Point-in-region test for orthogonal loops, in exact integers.
+/// `p` is given in doubled coordinates, so half-integer points such as cell
+/// centres are representable. The region is closed: a point on a loop belongs
+/// to it when one of the four quadrants around the point is inside under the
+/// fill rule, matching the closure of inside cells used by the grid.
+fn point_in_region(p: (i128, i128), region: &Region) -> bool {
+    let (px, py) = p;
+    let on_boundary = region.edges().any(|(a, b)| {
+        let (x1, y1) = (2 * a.x as i128, 2 * a.y as i128);
+        let (x2, y2) = (2 * b.x as i128, 2 * b.y as i128);
+        (x1.min(x2)..=x1.max(x2)).contains(&px) && (y1.min(y2)..=y1.max(y2)).contains(&py)
+    });
+    if !on_boundary {
+        return region.rule.is_inside(winding(p, region, 2));
+    }
+
+    // in quadrupled coordinates the edges lie on multiples of 4, so odd probes
+    // next to the point are off every edge
+    let (qx, qy) = (2 * px, 2 * py);
+    [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+        .iter()
+        .any(|&(dx, dy)| {
+            region
+                .rule
+                .is_inside(winding((qx + dx, qy + dy), region, 4))
+        })
 }
 
-/// Build a compressed grid from polygon vertex coordinates:
-/// X coords are unique vertex x's; Y coords are unique vertex y's.
-/// Then determine which cells are inside the polygon by testing cell centers.
-/// Finally, build a weighted prefix sum over cell areas (in continuous area units).
-fn build_allowed_prefix(poly: &[Point2D]) -> (Vec<i64>, Vec<i64>, Vec<Vec<i128>>) {
+/// Sorted unique vertex x's and y's: the lines of the compressed grid.
+fn compress(region: &Region) -> (Vec<i64>, Vec<i64>) {
     let mut xs_set = BTreeSet::<i64>::new();
     let mut ys_set = BTreeSet::<i64>::new();
-    for p in poly {
+    for p in region.points() {
         xs_set.insert(p.x);
         ys_set.insert(p.y);
     }
-    let xs: Vec<i64> = xs_set.into_iter().collect();
-    let ys: Vec<i64> = ys_set.into_iter().collect();
+    (xs_set.into_iter().collect(), ys_set.into_iter().collect())
+}
+
+/// Scanline sweep over the compressed grid rows. For every cell row `yi`
+/// (between `ys[yi]` and `ys[yi + 1]`) returns the sorted x-indices where the
+/// row enters or leaves the region under its fill rule, so the inside cells of
+/// the row are `[c[0], c[1])`, `[c[2], c[3])`, ...
+/// Runs in O(n log n + total crossings), i.e. O(R^2) in the worst case.
+fn scan_rows(region: &Region, xs: &[i64], ys: &[i64]) -> Vec<Vec<usize>> {
+    let h = ys.len().saturating_sub(1);
+    // (x-index, direction) of vertical edges starting / ending on each y line
+    let mut starts: Vec<Vec<(usize, i64)>> = vec![Vec::new(); h + 1];
+    let mut ends: Vec<Vec<(usize, i64)>> = vec![Vec::new(); h + 1];
+    for (a, b) in region.edges() {
+        if a.x != b.x || a.y == b.y {
+            continue; // only vertical edges cross rows
+        }
+        let dir = if b.y > a.y { 1 } else { -1 };
+        let ix = xs.binary_search(&a.x).expect("coord not in lines");
+        let iy1 = ys.binary_search(&a.y.min(b.y)).expect("coord not in lines");
+        let iy2 = ys.binary_search(&a.y.max(b.y)).expect("coord not in lines");
+        starts[iy1].push((ix, dir));
+        ends[iy2].push((ix, dir));
+    }
+
+    // summed direction of the active edges at each x-index
+    let mut active = BTreeMap::<usize, i64>::new();
+    let mut rows = Vec::with_capacity(h);
+    for yi in 0..h {
+        for &(ix, dir) in &ends[yi] {
+            *active.entry(ix).or_insert(0) -= dir;
+        }
+        for &(ix, dir) in &starts[yi] {
+            *active.entry(ix).or_insert(0) += dir;
+        }
+        active.retain(|_, d| *d != 0);
+
+        let mut boundaries = Vec::new();
+        let mut winding = 0;
+        let mut inside = false;
+        for (&ix, &dir) in &active {
+            winding += dir;
+            if region.rule.is_inside(winding) != inside {
+                inside = !inside;
+                boundaries.push(ix);
+            }
+        }
+        rows.push(boundaries);
+    }
+    rows
+}
+
+/// Cell range `[l, r)` of the inside run containing cell column `xi`, if any.
+fn inside_run(crossings: &[usize], xi: usize) -> Option<(usize, usize)> {
+    let k = crossings.partition_point(|&c| c <= xi);
+    if k % 2 == 1 && k < crossings.len() {
+        Some((crossings[k - 1], crossings[k]))
+    } else {
+        None
+    }
+}
+
+/// Build a compressed grid from polygon vertex coordinates:
+/// X coords are unique vertex x's; Y coords are unique vertex y's.
+/// Then determine which cells are inside the region with a scanline sweep over the rows,
+/// so cells in holes stay empty.
+/// Finally, build a weighted prefix sum over cell areas (in continuous area units).
+fn build_allowed_prefix(region: &Region) -> (Vec<i64>, Vec<i64>, Vec<Vec<i128>>) {
+    let (xs, ys) = compress(region);
 
     // cells exist between consecutive coordinate lines
     let w = xs.len().saturating_sub(1);
@@ -124,19 +504,11 @@ fn build_allowed_prefix(poly: &[Point2D]) -> (Vec<i64>, Vec<i64>, Vec<Vec<i128>>
     // A[y][x] = area of cell if inside else 0 (use i128 to avoid overflow)
     let mut a = vec![vec![0i128; w]; h];
 
-    for yi in 0..h {
-        let y0 = ys[yi] as f64;
-        let y1 = ys[yi + 1] as f64;
-        let cy = (y0 + y1) / 2.0;
+    for (yi, crossings) in scan_rows(region, &xs, &ys).iter().enumerate() {
         let cell_h = (ys[yi + 1] - ys[yi]) as i128;
-
-        for xi in 0..w {
-            let x0 = xs[xi] as f64;
-            let x1 = xs[xi + 1] as f64;
-            let cx = (x0 + x1) / 2.0;
-            let cell_w = (xs[xi + 1] - xs[xi]) as i128;
-
-            if point_in_poly_even_odd((cx, cy), poly) {
+        for run in crossings.chunks_exact(2) {
+            for xi in run[0]..run[1] {
+                let cell_w = (xs[xi + 1] - xs[xi]) as i128;
                 a[yi][xi] = cell_w * cell_h;
             }
         }
@@ -160,67 +532,437 @@ fn rect_sum(p: &[Vec<i128>], x1: usize, y1: usize, x2: usize, y2: usize) -> i128
     p[y2][x2] - p[y1][x2] - p[y2][x1] + p[y1][x1]
 }
 
-pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let points: Vec<Point2D> = lines
-        .filter_map(|line| {
-            let line = line.ok()?;
-            Point2D::from_string(&line).ok()
-        })
-        .collect();
+/// The compressed prefix grid of a region together with its red tiles, built
+/// once so that many rectangle questions can be asked without rebuilding it.
+/// The allowed region is the closure of the inside cells.
+pub struct AllowedGrid {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    pref: Vec<Vec<i128>>,
+    red: Vec<Point2D>,
+}
+
+impl AllowedGrid {
+    pub fn new(region: &Region) -> Self {
+        let (xs, ys, pref) = build_allowed_prefix(region);
+        AllowedGrid {
+            xs,
+            ys,
+            pref,
+            red: region.points().copied().collect(),
+        }
+    }
+
+    /// Reads and validates the loops, then builds the grid.
+    pub fn from_lines(
+        lines: impl Iterator<Item = io::Result<String>>,
+        rule: FillRule,
+    ) -> io::Result<Self> {
+        let region = Region::from_lines(lines, rule)?;
+        region.validate()?;
+        Ok(AllowedGrid::new(&region))
+    }
 
-    // Build compressed-grid prefix sum of allowed interior (continuous).
-    let (xs, ys, pref) = build_allowed_prefix(&points);
+    /// Whether the closed rectangle with corners `a` and `b` is allowed.
+    /// Both corners must lie on grid lines, as red tiles do.
+    pub fn contains(&self, a: &Point2D, b: &Point2D) -> bool {
+        let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+        let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+        let ix1 = self.xs.binary_search(&min_x).expect("coord not in lines");
+        let ix2 = self.xs.binary_search(&max_x).expect("coord not in lines");
+        let iy1 = self.ys.binary_search(&min_y).expect("coord not in lines");
+        let iy2 = self.ys.binary_search(&max_y).expect("coord not in lines");
+        let rect_area_cont = (max_x - min_x) as i128 * (max_y - min_y) as i128;
+        rect_sum(&self.pref, ix1, iy1, ix2, iy2) == rect_area_cont
+    }
 
-    let mut best: i64 = 0;
+    fn cell_inside(&self, xi: usize, yi: usize) -> bool {
+        rect_sum(&self.pref, xi, yi, xi + 1, yi + 1) > 0
+    }
 
-    // Enumerate opposite-corner red pairs (O(R^2))
-    for i in 0..points.len() {
-        for j in (i + 1)..points.len() {
-            let a = &points[i];
-            let b = &points[j];
+    /// Allowed non-degenerate rectangles with two red corners (O(R^2)).
+    fn red_rectangles(&self) -> impl Iterator<Item = Rectangle> + '_ {
+        self.red
+            .iter()
+            .tuple_combinations()
+            .filter(|(a, b)| a.x != b.x && a.y != b.y && self.contains(a, b))
+            .map(|(a, b)| Rectangle::new(*a, *b))
+    }
 
-            if a.x == b.x || a.y == b.y {
-                continue; // degenerate rectangle (line)
+    /// Largest allowed rectangle with two red corners.
+    pub fn largest_red_rectangle(&self) -> Option<Rectangle> {
+        self.red_rectangles().max_by_key(|r| r.area)
+    }
+
+    /// Largest allowed rectangle with two red corners whose size in tiles is
+    /// exactly `width`:`height`.
+    pub fn largest_with_aspect(&self, width: i64, height: i64) -> Option<Rectangle> {
+        self.red_rectangles()
+            .filter(|r| {
+                let w = (r.a.x - r.b.x).abs() as i128 + 1;
+                let h = (r.a.y - r.b.y).abs() as i128 + 1;
+                w * height as i128 == h * width as i128
+            })
+            .max_by_key(|r| r.area)
+    }
+
+    /// Number of red tile pairs spanning an allowed non-degenerate rectangle.
+    pub fn count_valid_pairs(&self) -> usize {
+        self.red_rectangles().count()
+    }
+
+    /// Largest allowed rectangle anywhere, corners not necessarily red.
+    /// An optimal rectangle always has its sides on grid lines, so every pair of
+    /// rows is tried and the widest run of fully allowed columns kept (O(h^2 w)).
+    pub fn largest_rectangle(&self) -> Option<Rectangle> {
+        let (w, h) = (
+            self.xs.len().saturating_sub(1),
+            self.ys.len().saturating_sub(1),
+        );
+        let mut best: Option<Rectangle> = None;
+        for iy1 in 0..h {
+            for iy2 in (iy1 + 1)..=h {
+                let strip_h = (self.ys[iy2] - self.ys[iy1]) as i128;
+                let mut run_start: Option<usize> = None;
+                for xi in 0..=w {
+                    let allowed = xi < w && {
+                        let cell_w = (self.xs[xi + 1] - self.xs[xi]) as i128;
+                        rect_sum(&self.pref, xi, iy1, xi + 1, iy2) == cell_w * strip_h
+                    };
+                    match (allowed, run_start) {
+                        (true, None) => run_start = Some(xi),
+                        (false, Some(ix1)) => {
+                            let rect = Rectangle::new(
+                                Point2D {
+                                    x: self.xs[ix1],
+                                    y: self.ys[iy1],
+                                },
+                                Point2D {
+                                    x: self.xs[xi],
+                                    y: self.ys[iy2],
+                                },
+                            );
+                            if best.is_none_or(|b| rect.area > b.area) {
+                                best = Some(rect);
+                            }
+                            run_start = None;
+                        }
+                        _ => {}
+                    }
+                }
             }
+        }
+        best
+    }
 
-            // Rectangle corners in tile coords (inclusive tiles):
-            // Use min/max for area in tiles
-            let min_x = a.x.min(b.x);
-            let max_x = a.x.max(b.x);
-            let min_y = a.y.min(b.y);
-            let max_y = a.y.max(b.y);
+    /// Number of integer tiles in the allowed region: interior points of inside
+    /// cells, plus every grid segment and grid vertex touching an inside cell.
+    pub fn enclosed_tiles(&self) -> i128 {
+        let (w, h) = (
+            self.xs.len().saturating_sub(1),
+            self.ys.len().saturating_sub(1),
+        );
+        let inside = |xi: usize, yi: usize| xi < w && yi < h && self.cell_inside(xi, yi);
+        let mut tiles: i128 = 0;
+        for yi in 0..=h {
+            for xi in 0..=w {
+                let here = inside(xi, yi);
+                let left = xi > 0 && inside(xi - 1, yi);
+                let up = yi > 0 && inside(xi, yi - 1);
+                let up_left = xi > 0 && yi > 0 && inside(xi - 1, yi - 1);
+                if xi < w && yi < h && here {
+                    let dx = (self.xs[xi + 1] - self.xs[xi]) as i128;
+                    let dy = (self.ys[yi + 1] - self.ys[yi]) as i128;
+                    tiles += (dx - 1) * (dy - 1);
+                }
+                // open horizontal segment from (xs[xi], ys[yi]) to the right
+                if xi < w && (here || up) {
+                    tiles += (self.xs[xi + 1] - self.xs[xi]) as i128 - 1;
+                }
+                // open vertical segment from (xs[xi], ys[yi]) downwards
+                if yi < h && (here || left) {
+                    tiles += (self.ys[yi + 1] - self.ys[yi]) as i128 - 1;
+                }
+                if here || left || up || up_left {
+                    tiles += 1;
+                }
+            }
+        }
+        tiles
+    }
+}
 
-            // Quick upper bound pruning
-            let area_tiles = rect_area_tiles(a, b);
-            if area_tiles <= best {
+/// Largest rectangle with two red corners lying entirely inside the region.
+///
+/// Every non-degenerate pair has an upper corner `a`, so for each `a` we walk the
+/// cell rows below it, once towards +x and once towards -x. While walking, the
+/// inside run containing `a`'s column can only shrink, which gives a staircase
+/// bound `limit`; the best partner on the row's lower line is the red tile
+/// farthest from `a` within that bound. Corners are visited in decreasing order
+/// of an area upper bound, so the search stops as soon as no corner can win.
+fn best_rectangle(region: &Region) -> Option<Rectangle> {
+    let points: Vec<Point2D> = region.points().copied().collect();
+    let (xs, ys) = compress(region);
+    let rows = scan_rows(region, &xs, &ys);
+    let (w, h) = (xs.len().saturating_sub(1), ys.len().saturating_sub(1));
+    if w == 0 || h == 0 {
+        return None;
+    }
+
+    // grid index of every red tile, and red x-indices on each y line
+    let idx: Vec<(usize, usize)> = points
+        .iter()
+        .map(|p| {
+            let ix = xs.binary_search(&p.x).expect("coord not in lines");
+            let iy = ys.binary_search(&p.y).expect("coord not in lines");
+            (ix, iy)
+        })
+        .collect();
+    let mut red_on_line: Vec<Vec<usize>> = vec![Vec::new(); h + 1];
+    for &(ix, iy) in &idx {
+        red_on_line[iy].push(ix);
+    }
+    for line in red_on_line.iter_mut() {
+        line.sort_unstable();
+    }
+
+    let bound = |p: &Point2D| {
+        let dx = (p.x - xs[0]).max(xs[w] - p.x);
+        (dx + 1) * (ys[h] - p.y + 1)
+    };
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(bound(&points[i])));
+
+    let mut best: Option<Rectangle> = None;
+    for i in order {
+        let a = points[i];
+        if best.is_some_and(|r| bound(&a) <= r.area) {
+            break;
+        }
+        let (ia, ja) = idx[i];
+
+        for to_right in [true, false] {
+            if !to_right && ia == 0 {
                 continue;
             }
+            // cell column just beside `a` in the walking direction
+            let col = if to_right { ia } else { ia - 1 };
+            let mut limit = if to_right { w } else { 0 };
+            for (yi, crossings) in rows.iter().enumerate().skip(ja) {
+                let Some((l, r)) = inside_run(crossings, col) else {
+                    break;
+                };
+                limit = if to_right { limit.min(r) } else { limit.max(l) };
+
+                let far = (xs[limit] - a.x).abs() + 1;
+                if best.is_some_and(|b| far * (ys[h] - a.y + 1) <= b.area) {
+                    break;
+                }
+
+                let line = &red_on_line[yi + 1];
+                let partner = if to_right {
+                    let k = line.partition_point(|&x| x <= limit);
+                    line[..k].last().filter(|&&x| x > ia)
+                } else {
+                    let k = line.partition_point(|&x| x < limit);
+                    line[k..].first().filter(|&&x| x < ia)
+                };
+                if let Some(&ib) = partner {
+                    let rect = Rectangle::new(
+                        a,
+                        Point2D {
+                            x: xs[ib],
+                            y: ys[yi + 1],
+                        },
+                    );
+                    if best.is_none_or(|b| rect.area > b.area) {
+                        best = Some(rect);
+                    }
+                }
+            }
+        }
+    }
+
+    best
+}
+
+/// Checks that the closed rectangle with corners `a` and `b` lies inside `region`
+/// without using the compressed grid: the rectangle is cut along every edge that
+/// meets it, and one point of each resulting piece (cell, segment or corner) is
+/// tested with `point_in_region`. Works for degenerate (zero-width) rectangles too.
+pub fn verify_rectangle(region: &Region, a: Point2D, b: Point2D) -> bool {
+    // doubled coordinates, so midpoints stay integral
+    let (x1, x2) = (2 * a.x.min(b.x) as i128, 2 * a.x.max(b.x) as i128);
+    let (y1, y2) = (2 * a.y.min(b.y) as i128, 2 * a.y.max(b.y) as i128);
 
-            // For the continuous prefix grid:
-            // we want the area of the rectangle [min_x, max_x] x [min_y, max_y]
-            // in coordinate space. This requires these coordinates to be in xs/ys.
-            let ix1 = xs.binary_search(&min_x).expect("coord not in lines");
-            let ix2 = xs.binary_search(&max_x).expect("coord not in lines");
-            let iy1 = ys.binary_search(&min_y).expect("coord not in lines");
-            let iy2 = ys.binary_search(&max_y).expect("coord not in lines");
+    let mut cut_xs = vec![x1, x2];
+    let mut cut_ys = vec![y1, y2];
+    for (p, q) in region.edges() {
+        let (ex1, ex2) = (2 * p.x.min(q.x) as i128, 2 * p.x.max(q.x) as i128);
+        let (ey1, ey2) = (2 * p.y.min(q.y) as i128, 2 * p.y.max(q.y) as i128);
+        if ex1 <= x2 && ex2 >= x1 && ey1 <= y2 && ey2 >= y1 {
+            cut_xs.extend([ex1.max(x1), ex2.min(x2)]);
+            cut_ys.extend([ey1.max(y1), ey2.min(y2)]);
+        }
+    }
+
+    // every cut line plus the midpoint between consecutive ones
+    let samples = |mut cuts: Vec<i128>| {
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mids: Vec<i128> = cuts
+            .iter()
+            .tuple_windows()
+            .map(|(p, q)| (p + q) / 2)
+            .collect();
+        cuts.extend(mids);
+        cuts
+    };
+    let sample_ys = samples(cut_ys);
+    samples(cut_xs)
+        .into_iter()
+        .all(|x| sample_ys.iter().all(|&y| point_in_region((x, y), region)))
+}
+
+/// Side length, in pixels, of the longer axis of the rendered drawing.
+const SVG_SIZE: f64 = 800.0;
+const SVG_MARGIN: f64 = 10.0;
 
-            // Cells are between lines, so rectangle covering [min_x,max_x] spans cell indices [ix1,ix2)
-            // BUT since max_x is a line, to include the region up to max_x, we use ix2 (already the larger index)
-            // same for y.
-            let allowed_area = rect_sum(&pref, ix1, iy1, ix2, iy2);
+/// Renders the red-tile loops as an SVG document, scaled to fit `SVG_SIZE`
+/// whatever the coordinate magnitude. With `show_grid`, the compressed grid
+/// lines from `build_allowed_prefix` are drawn and inside cells shaded; `best`
+/// is highlighted on top.
+fn render_svg(region: &Region, show_grid: bool, best: Option<&Rectangle>) -> String {
+    let min_x = region.points().map(|p| p.x).min().unwrap_or(0);
+    let max_x = region.points().map(|p| p.x).max().unwrap_or(0);
+    let min_y = region.points().map(|p| p.y).min().unwrap_or(0);
+    let max_y = region.points().map(|p| p.y).max().unwrap_or(0);
+    // i128 differences so that spans near i64::MAX do not overflow
+    let span = ((max_x as i128 - min_x as i128).max(max_y as i128 - min_y as i128)).max(1);
+    let scale = SVG_SIZE / span as f64;
+    let sx = |x: i64| SVG_MARGIN + (x as i128 - min_x as i128) as f64 * scale;
+    let sy = |y: i64| SVG_MARGIN + (y as i128 - min_y as i128) as f64 * scale;
+    let width = sx(max_x) + SVG_MARGIN;
+    let height = sy(max_y) + SVG_MARGIN;
 
-            // Rectangle continuous area:
-            let rect_area_cont = (max_x - min_x) as i128 * (max_y - min_y) as i128;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.2} {:.2}">"#,
+        width.ceil(),
+        height.ceil(),
+        width,
+        height
+    );
 
-            // If fully inside (continuous), accept.
-            // (If you need exact *tile* coverage, this is the spot to adjust with +1 / scaling.)
-            if allowed_area == rect_area_cont {
-                best = area_tiles;
+    if show_grid {
+        let (xs, ys, pref) = build_allowed_prefix(region);
+        for yi in 0..ys.len().saturating_sub(1) {
+            for xi in 0..xs.len().saturating_sub(1) {
+                if rect_sum(&pref, xi, yi, xi + 1, yi + 1) > 0 {
+                    let _ = writeln!(
+                        svg,
+                        r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#cde8cd"/>"##,
+                        sx(xs[xi]),
+                        sy(ys[yi]),
+                        sx(xs[xi + 1]) - sx(xs[xi]),
+                        sy(ys[yi + 1]) - sy(ys[yi])
+                    );
+                }
             }
         }
+        for &x in &xs {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{0:.2}" y1="{1:.2}" x2="{0:.2}" y2="{2:.2}" stroke="#bbbbbb" stroke-width="0.5"/>"##,
+                sx(x),
+                sy(min_y),
+                sy(max_y)
+            );
+        }
+        for &y in &ys {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{1:.2}" y1="{0:.2}" x2="{2:.2}" y2="{0:.2}" stroke="#bbbbbb" stroke-width="0.5"/>"##,
+                sy(y),
+                sx(min_x),
+                sx(max_x)
+            );
+        }
     }
 
-    Ok(best)
+    for l in &region.loops {
+        let points: Vec<String> = l
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", sx(p.x), sy(p.y)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r##"<polygon points="{}" fill="none" stroke="#2e7d32" stroke-width="1.5"/>"##,
+            points.join(" ")
+        );
+    }
+    for p in region.points() {
+        let _ = writeln!(
+            svg,
+            r##"<circle cx="{:.2}" cy="{:.2}" r="2" fill="#d32f2f"/>"##,
+            sx(p.x),
+            sy(p.y)
+        );
+    }
+
+    if let Some(r) = best {
+        let (x0, x1) = (r.a.x.min(r.b.x), r.a.x.max(r.b.x));
+        let (y0, y1) = (r.a.y.min(r.b.y), r.a.y.max(r.b.y));
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#1565c0" fill-opacity="0.3" stroke="#1565c0" stroke-width="2"/>"##,
+            sx(x0),
+            sy(y0),
+            sx(x1) - sx(x0),
+            sy(y1) - sy(y0)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Part 2 witness: the largest red-cornered rectangle inside the region
+/// filled under `rule`, `None` if there are no red tiles.
+pub fn witness_part_2(
+    lines: impl Iterator<Item = io::Result<String>>,
+    rule: FillRule,
+) -> io::Result<Option<Rectangle>> {
+    let region = Region::from_lines(lines, rule)?;
+    region.validate()?;
+
+    Ok(best_rectangle(&region))
+}
+
+/// SVG drawing of the region filled under `rule` with the part 2 rectangle
+/// highlighted, see `render_svg`.
+pub fn svg_part_2(
+    lines: impl Iterator<Item = io::Result<String>>,
+    rule: FillRule,
+    show_grid: bool,
+) -> io::Result<String> {
+    let region = Region::from_lines(lines, rule)?;
+    region.validate()?;
+
+    let best = best_rectangle(&region);
+    Ok(render_svg(&region, show_grid, best.as_ref()))
+}
+
+pub fn solve_with_rule(
+    lines: impl Iterator<Item = io::Result<String>>,
+    rule: FillRule,
+) -> io::Result<i64> {
+    Ok(witness_part_2(lines, rule)?.map_or(0, |r| r.area))
+}
+
+pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    solve_with_rule(lines, FillRule::EvenOdd)
 }
 
 #[cfg(test)]
@@ -247,4 +989,351 @@ mod tests {
         let result = solve(input.into_iter()).unwrap();
         assert_eq!(result, 24);
     }
+
+    fn poly(coords: &[(i64, i64)]) -> Vec<Point2D> {
+        coords.iter().map(|&(x, y)| Point2D { x, y }).collect()
+    }
+
+    fn simple(poly: Vec<Point2D>) -> Region {
+        Region::new(vec![poly], FillRule::EvenOdd)
+    }
+
+    #[test]
+    fn test_point_in_poly_large_coords() {
+        let big = 1i64 << 60;
+        let square = simple(poly(&[
+            (big, big),
+            (big + 2, big),
+            (big + 2, big + 2),
+            (big, big + 2),
+        ]));
+        let c = 2 * big as i128;
+        assert!(point_in_region((c + 1, c + 1), &square));
+        assert!(point_in_region((c, c + 3), &square));
+        assert!(!point_in_region((c - 1, c + 1), &square));
+        assert!(!point_in_region((c + 5, c + 1), &square));
+    }
+
+    #[test]
+    fn test_validate_polygon() {
+        // vertices numbered as lines 1, 2, ...
+        let check = |coords: &[(i64, i64)]| {
+            validate_polygon(&poly(coords), &(1..=coords.len()).collect_vec())
+        };
+        assert_eq!(
+            check(&[(0, 0), (2, 0), (2, 2)]),
+            Err(PolygonError::TooFewVertices(3))
+        );
+        assert_eq!(
+            check(&[(0, 0), (2, 0), (2, 2), (1, 3), (0, 2)]),
+            Err(PolygonError::DiagonalEdge { line: 3 })
+        );
+        assert_eq!(
+            check(&[(0, 0), (2, 0), (2, 2), (0, 2), (0, 0), (1, 0)]),
+            Err(PolygonError::RepeatedVertex {
+                first: 1,
+                second: 5
+            })
+        );
+        assert_eq!(
+            check(&[(0, 0), (4, 0), (4, 2), (4, 1), (0, 1)]),
+            Err(PolygonError::Spur { line: 3 })
+        );
+        // figure-eight: edges from lines 2 and 5 cross
+        assert_eq!(
+            check(&[(0, 0), (2, 0), (2, 4), (4, 4), (4, 2), (0, 2)]),
+            Err(PolygonError::SelfIntersection {
+                first: 2,
+                second: 5
+            })
+        );
+        // edges from lines 1 and 5 overlap along y = 0
+        assert_eq!(
+            check(&[
+                (0, 0),
+                (4, 0),
+                (4, -2),
+                (6, -2),
+                (6, 0),
+                (3, 0),
+                (3, 2),
+                (0, 2)
+            ]),
+            Err(PolygonError::SelfIntersection {
+                first: 1,
+                second: 5
+            })
+        );
+        assert_eq!(check(&[(0, 0), (2, 0), (2, 2), (0, 2)]), Ok(()));
+
+        // the second loop starts after a blank line, on line 6
+        let input = [
+            "0,0", "4,0", "4,4", "0,4", "", "1,1", "2,1", "2,2", "3,3", "1,2",
+        ];
+        let lines = input.iter().map(|l| Ok(l.to_string()));
+        let error = Region::from_lines(lines, FillRule::EvenOdd)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "loop from line 6: edge from line 8 is not axis-aligned"
+        );
+    }
+
+    #[test]
+    fn test_large_collinear_vertex() {
+        // (2^33, 0) sits in the middle of a straight edge
+        let big = 1i64 << 33;
+        let input = [
+            String::from("0,0"),
+            format!("{},0", big),
+            format!("{},0", 2 * big),
+            format!("{},1", 2 * big),
+            String::from("0,1"),
+        ];
+        let lines = input.into_iter().map(Ok);
+        assert_eq!(solve(lines).unwrap(), (2 * big + 1) * 2);
+    }
+
+    #[test]
+    fn test_witness_rectangles() {
+        let red = poly(&[
+            (7, 1),
+            (11, 1),
+            (11, 7),
+            (9, 7),
+            (9, 5),
+            (2, 5),
+            (2, 3),
+            (7, 3),
+        ]);
+        let best = best_rectangle_part_1(&red).unwrap();
+        assert_eq!(best.area, 50);
+        let red = simple(red);
+        assert!(!verify_rectangle(&red, best.a, best.b));
+
+        let best = best_rectangle(&red).unwrap();
+        assert_eq!(best.area, 24);
+        assert!(verify_rectangle(&red, best.a, best.b));
+
+        let tiles = red.loops[0].clone();
+        assert!(verify_rectangle(&red, tiles[6], tiles[5]));
+        assert!(verify_rectangle(&red, tiles[5], Point2D { x: 11, y: 5 }));
+        assert!(!verify_rectangle(&red, Point2D { x: 2, y: 1 }, tiles[1]));
+        assert!(!verify_rectangle(&red, tiles[6], tiles[0]));
+
+        let input = ["7,1", "11,1", "11,7", "9,7", "9,5", "2,5", "2,3", "7,3"];
+        let lines = || input.iter().map(|l| Ok(l.to_string()));
+        let part_1 = witness_part_1(lines()).unwrap();
+        assert_eq!(part_1.area, 50);
+        // both corners are red tiles, 9 x 6 apart
+        assert!(tiles.contains(&part_1.a) && tiles.contains(&part_1.b));
+        assert_eq!((part_1.a.x - part_1.b.x).abs(), 9);
+        let part_2 = witness_part_2(lines(), FillRule::EvenOdd).unwrap().unwrap();
+        assert_eq!(part_2.area, 24);
+        assert!(verify_rectangle(&red, part_2.a, part_2.b));
+    }
+
+    /// Staircase with `m` steps of size 10 going down from (0, 10m) to (10m, 0).
+    fn staircase(m: i64) -> Vec<Point2D> {
+        let mut coords = vec![(0, 0), (10 * m, 0)];
+        for k in 1..=m {
+            coords.push((10 * (m - k + 1), 10 * k));
+            coords.push((10 * (m - k), 10 * k));
+        }
+        poly(&coords)
+    }
+
+    #[test]
+    fn test_best_rectangle_matches_brute() {
+        let shapes = vec![
+            // L
+            poly(&[(0, 0), (6, 0), (6, 2), (2, 2), (2, 9), (0, 9)]),
+            // U
+            poly(&[
+                (0, 0),
+                (9, 0),
+                (9, 8),
+                (6, 8),
+                (6, 3),
+                (3, 3),
+                (3, 8),
+                (0, 8),
+            ]),
+            // plus sign
+            poly(&[
+                (3, 0),
+                (5, 0),
+                (5, 3),
+                (8, 3),
+                (8, 5),
+                (5, 5),
+                (5, 8),
+                (3, 8),
+                (3, 5),
+                (0, 5),
+                (0, 3),
+                (3, 3),
+            ]),
+            staircase(30),
+        ];
+        for shape in shapes.into_iter().map(simple) {
+            let fast = best_rectangle(&shape).unwrap();
+            let brute = AllowedGrid::new(&shape).largest_red_rectangle().unwrap();
+            assert_eq!(fast.area, brute.area);
+            assert!(verify_rectangle(&shape, fast.a, fast.b));
+        }
+    }
+
+    #[test]
+    fn test_best_rectangle_many_vertices() {
+        let m = 20_000;
+        let lines = staircase(m)
+            .into_iter()
+            .map(|p| Ok(format!("{},{}", p.x, p.y)));
+        // (0, 0) against the outer corner of the middle step
+        let k = m / 2;
+        assert_eq!(solve(lines).unwrap(), (10 * (m - k + 1) + 1) * (10 * k + 1));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let big = 1i64 << 62;
+        let red = poly(&[
+            (big, big),
+            (big + 1000, big),
+            (big + 1000, big + 500),
+            (big, big + 500),
+        ]);
+        let best = Rectangle::new(red[0], red[2]);
+        let red = simple(red);
+        let svg = render_svg(&red, true, Some(&best));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // one shaded cell, two grid lines per axis, the polygon and the highlight
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert!(svg.contains(r#"width="820" height="420""#));
+    }
+
+    #[test]
+    fn test_holes_and_fill_rules() {
+        let input_str = "
+            0,0
+            10,0
+            10,10
+            0,10
+
+            3,3
+            6,3
+            6,6
+            3,6
+
+            20,0
+            22,0
+            22,2
+            20,2";
+        let read = |rule| {
+            let input = input_str.lines().map(|line| Ok(line.trim().to_string()));
+            Region::from_lines(input, rule).unwrap()
+        };
+
+        // the witnesses of both rules pass the grid-free check
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let input = input_str.lines().map(|line| Ok(line.trim().to_string()));
+            let best = witness_part_2(input, rule).unwrap().unwrap();
+            assert!(verify_rectangle(&read(rule), best.a, best.b));
+        }
+
+        let even_odd = read(FillRule::EvenOdd);
+        assert_eq!(even_odd.loops.len(), 3);
+        assert!(!point_in_region((9, 9), &even_odd));
+        assert!(point_in_region((3, 3), &even_odd));
+        // (6,3) - (10,10), touching the hole only along its edge
+        assert_eq!(best_rectangle(&even_odd).unwrap().area, 40);
+        assert_eq!(
+            AllowedGrid::new(&even_odd)
+                .largest_red_rectangle()
+                .unwrap()
+                .area,
+            40
+        );
+
+        // same orientation: the inner loop does not cut a hole
+        let non_zero = read(FillRule::NonZero);
+        assert!(point_in_region((9, 9), &non_zero));
+        assert_eq!(best_rectangle(&non_zero).unwrap().area, 121);
+
+        // reversed inner loop is a hole again
+        let mut reversed = non_zero.clone();
+        reversed.loops[1].reverse();
+        assert!(!point_in_region((9, 9), &reversed));
+        let best = best_rectangle(&reversed).unwrap();
+        assert_eq!(best.area, 40);
+        assert!(verify_rectangle(&reversed, best.a, best.b));
+        assert!(!verify_rectangle(
+            &reversed,
+            Point2D { x: 0, y: 0 },
+            Point2D { x: 10, y: 10 }
+        ));
+
+        // the overlap of two loops is a hole whose top edge y = 9 also bounds
+        // the outside, so the open stretch 8 < x < 14 is not in the region
+        let touching = Region::new(
+            vec![
+                poly(&[(7, 9), (14, 9), (14, 7), (7, 7)]),
+                poly(&[(8, 3), (16, 3), (16, 9), (8, 9)]),
+            ],
+            FillRule::EvenOdd,
+        );
+        assert!(!point_in_region((20, 18), &touching));
+        assert!(point_in_region((16, 18), &touching));
+        assert!(point_in_region((28, 18), &touching));
+        let p = |x, y| Point2D { x, y };
+        assert!(!verify_rectangle(&touching, p(9, 8), p(12, 9)));
+        assert!(verify_rectangle(&touching, p(14, 3), p(16, 9)));
+        let brute_tiles = (0..=20)
+            .cartesian_product(0..=12)
+            .filter(|&(x, y)| point_in_region((2 * x, 2 * y), &touching))
+            .count();
+        assert_eq!(
+            AllowedGrid::new(&touching).enclosed_tiles(),
+            brute_tiles as i128
+        );
+    }
+
+    #[test]
+    fn test_allowed_grid_queries() {
+        let red = simple(poly(&[
+            (7, 1),
+            (11, 1),
+            (11, 7),
+            (9, 7),
+            (9, 5),
+            (2, 5),
+            (2, 3),
+            (7, 3),
+        ]));
+        let grid = AllowedGrid::new(&red);
+        assert_eq!(grid.largest_red_rectangle().unwrap().area, 24);
+        // (2,3)-(11,5): 10 x 3 tiles, corner (11,5) is not red
+        assert_eq!(grid.largest_rectangle().unwrap().area, 30);
+        assert_eq!(grid.largest_with_aspect(3, 3).unwrap().area, 9);
+        assert_eq!(grid.largest_with_aspect(5, 3).unwrap().area, 15);
+        assert!(grid.largest_with_aspect(1, 100).is_none());
+
+        let brute_pairs = red.loops[0]
+            .iter()
+            .tuple_combinations()
+            .filter(|(a, b)| a.x != b.x && a.y != b.y && verify_rectangle(&red, **a, **b))
+            .count();
+        assert_eq!(grid.count_valid_pairs(), brute_pairs);
+
+        let brute_tiles = (0..=12)
+            .flat_map(|x| (0..=8).map(move |y| (2 * x, 2 * y)))
+            .filter(|&p| point_in_region(p, &red))
+            .count();
+        assert_eq!(grid.enclosed_tiles(), brute_tiles as i128);
+    }
 }
@@ -1,22 +1,194 @@
-use std::collections::{HashMap, VecDeque};
-use std::io;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::{error, fmt, io};
 
-fn parse(line: &str) -> (String, Vec<String>) {
-    let (source, dest) = line.split_once(":").unwrap();
+/// Errors raised while reading or walking the device graph.
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+    /// A line without the `name: outputs...` separator.
+    MissingColon { line: usize },
+    /// A line whose device name is empty or contains whitespace.
+    BadSource { line: usize },
+    /// A node was reached that has no definition line.
+    Undefined { node: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::MissingColon { line } => write!(f, "line {}: missing ':'", line),
+            GraphError::BadSource { line } => write!(f, "line {}: invalid device name", line),
+            GraphError::Undefined { node } => write!(f, "device '{}' is never defined", node),
+        }
+    }
+}
+
+impl error::Error for GraphError {}
+
+impl From<GraphError> for io::Error {
+    fn from(e: GraphError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// What to do when a path reaches a device that has no outgoing line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndefinedPolicy {
+    /// Fail with `GraphError::Undefined`.
+    Error,
+    /// Treat the device as a dead end that never reaches "out".
+    Sink,
+}
+
+/// Device graph plus the bookkeeping needed to validate it.
+#[derive(Debug, Default)]
+pub struct Graph {
+    edges: HashMap<String, Vec<String>>,
+    // line where each device was first defined
+    defined_at: HashMap<String, usize>,
+    // (device, line) of every definition after the first one
+    duplicates: Vec<(String, usize)>,
+}
+
+impl Graph {
+    /// Builds the graph from raw lines. Blank lines are skipped; line numbers are 1-based.
+    /// A duplicated device keeps its first definition; later ones are only recorded.
+    pub fn from_lines(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Self> {
+        let mut graph = Graph::default();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (source, dests) = parse(&line, i + 1)?;
+            if graph.defined_at.contains_key(&source) {
+                graph.duplicates.push((source, i + 1));
+            } else {
+                graph.defined_at.insert(source.clone(), i + 1);
+                graph.edges.insert(source, dests);
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn is_defined(&self, node: &str) -> bool {
+        self.edges.contains_key(node)
+    }
+
+    fn outputs(&self, node: &str, policy: UndefinedPolicy) -> Result<&[String], GraphError> {
+        match (self.edges.get(node), policy) {
+            (Some(dests), _) => Ok(dests),
+            (None, UndefinedPolicy::Sink) => Ok(&[]),
+            (None, UndefinedPolicy::Error) => Err(GraphError::Undefined {
+                node: node.to_string(),
+            }),
+        }
+    }
+
+    /// Checks the graph for problems, using `roots` as the starting devices
+    /// for reachability. Every list in the result is sorted.
+    pub fn validate(&self, roots: &[&str]) -> Validation {
+        let mut undefined: Vec<String> = self
+            .edges
+            .values()
+            .flatten()
+            .filter(|v| v.as_str() != "out" && !self.edges.contains_key(*v))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        undefined.sort();
+
+        let mut duplicates: Vec<(String, usize, usize)> = self
+            .duplicates
+            .iter()
+            .map(|(node, line)| (node.clone(), self.defined_at[node], *line))
+            .collect();
+        duplicates.sort();
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut q: VecDeque<&str> = roots.iter().copied().collect();
+        while let Some(node) = q.pop_front() {
+            if !seen.insert(node) {
+                continue;
+            }
+            if let Some(dests) = self.edges.get(node) {
+                q.extend(dests.iter().map(|d| d.as_str()));
+            }
+        }
+        let mut unreachable: Vec<String> = self
+            .edges
+            .keys()
+            .filter(|k| !seen.contains(k.as_str()))
+            .cloned()
+            .collect();
+        unreachable.sort();
+
+        Validation {
+            undefined,
+            duplicates,
+            unreachable,
+        }
+    }
+}
+
+/// Result of `Graph::validate`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Validation {
+    /// Devices used as an output but never defined ("out" excluded).
+    pub undefined: Vec<String>,
+    /// (device, first definition line, duplicate line)
+    pub duplicates: Vec<(String, usize, usize)>,
+    /// Defined devices that no root can reach.
+    pub unreachable: Vec<String>,
+}
+
+impl Validation {
+    pub fn is_clean(&self) -> bool {
+        self.undefined.is_empty() && self.duplicates.is_empty() && self.unreachable.is_empty()
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in &self.undefined {
+            writeln!(f, "undefined: {}", node)?;
+        }
+        for (node, first, line) in &self.duplicates {
+            writeln!(
+                f,
+                "duplicate: {} on line {} (first on line {})",
+                node, line, first
+            )?;
+        }
+        for node in &self.unreachable {
+            writeln!(f, "unreachable: {}", node)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse(line: &str, line_no: usize) -> Result<(String, Vec<String>), GraphError> {
+    let (source, dest) = line
+        .split_once(":")
+        .ok_or(GraphError::MissingColon { line: line_no })?;
+    let source = source.trim();
+    if source.is_empty() || source.contains(char::is_whitespace) {
+        return Err(GraphError::BadSource { line: line_no });
+    }
     let dests: Vec<String> = dest
         .trim()
         .split_ascii_whitespace()
         .map(|x| x.to_string())
         .collect();
-    (source.to_string(), dests)
+    Ok((source.to_string(), dests))
 }
 
-fn bfs(graph: &HashMap<String, Vec<String>>) -> u64 {
+fn bfs(graph: &Graph, policy: UndefinedPolicy) -> Result<u64, GraphError> {
     let mut q: VecDeque<String> = VecDeque::new();
     let mut tot: u64 = 0;
     q.push_back("you".to_string());
     while let Some(source) = q.pop_front() {
-        let to_visit = graph.get(&source).unwrap();
+        let to_visit = graph.outputs(&source, policy)?;
         for v in to_visit {
             if v != "out" {
                 q.push_back(v.clone());
@@ -25,72 +197,76 @@ fn bfs(graph: &HashMap<String, Vec<String>>) -> u64 {
             }
         }
     }
-    tot
+    Ok(tot)
 }
 
 fn dfs(
-    current: &String,
-    graph: &HashMap<String, Vec<String>>,
+    current: &str,
+    graph: &Graph,
+    policy: UndefinedPolicy,
     cache: &mut HashMap<String, u64>,
-) -> u64 {
+) -> Result<u64, GraphError> {
     if current == "out" {
-        return 1;
+        return Ok(1);
     }
-    if cache.contains_key(current) {
-        return *cache.get(current).unwrap();
+    if let Some(&cached) = cache.get(current) {
+        return Ok(cached);
     }
 
     let mut tot: u64 = 0;
-    let to_visit = graph.get(current).unwrap();
-    for node in to_visit {
-        tot += dfs(node, graph, cache);
+    for node in graph.outputs(current, policy)? {
+        tot += dfs(node, graph, policy, cache)?;
     }
-    cache.insert(current.clone(), tot);
-    tot
+    cache.insert(current.to_string(), tot);
+    Ok(tot)
 }
 
 fn dfs_part2(
     current: &str,
     has_dac: bool,
     has_fft: bool,
-    graph: &HashMap<String, Vec<String>>,
+    graph: &Graph,
+    policy: UndefinedPolicy,
     cache: &mut HashMap<(String, bool, bool), u64>,
-) -> u64 {
+) -> Result<u64, GraphError> {
     if current == "out" {
-        return if has_dac && has_fft { 1 } else { 0 };
+        return Ok(if has_dac && has_fft { 1 } else { 0 });
     }
     let key = (current.to_string(), has_dac, has_fft);
     if let Some(&cached) = cache.get(&key) {
-        return cached;
+        return Ok(cached);
     }
 
     let mut tot = 0;
-    if let Some(to_visit) = graph.get(current) {
-        for node in to_visit {
-            let new_has_dac = has_dac || node == "dac";
-            let new_has_fft = has_fft || node == "fft";
-            tot += dfs_part2(node, new_has_dac, new_has_fft, graph, cache);
-        }
+    for node in graph.outputs(current, policy)? {
+        let new_has_dac = has_dac || node == "dac";
+        let new_has_fft = has_fft || node == "fft";
+        tot += dfs_part2(node, new_has_dac, new_has_fft, graph, policy, cache)?;
     }
     cache.insert(key, tot);
-    tot
+    Ok(tot)
 }
 
-pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
-    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
-    for line in lines {
-        let line = line?;
-        let (source, dests) = parse(&line);
-        graph.insert(source, dests);
-    }
+pub fn solve_with_policy(
+    lines: impl Iterator<Item = io::Result<String>>,
+    policy: UndefinedPolicy,
+) -> io::Result<i64> {
+    let graph = Graph::from_lines(lines)?;
 
-    let ans = dfs(&String::from("you"), &graph, &mut HashMap::new());
-    let bfs_ans = bfs(&graph);
-    println!("bfs: {}, part1: {}", bfs_ans, ans);
-    let ans = dfs_part2("svr", false, false, &graph, &mut HashMap::new());
+    // an input made for part 2 alone has no "you" device
+    if graph.is_defined("you") {
+        let ans = dfs("you", &graph, policy, &mut HashMap::new())?;
+        let bfs_ans = bfs(&graph, policy)?;
+        println!("bfs: {}, part1: {}", bfs_ans, ans);
+    }
+    let ans = dfs_part2("svr", false, false, &graph, policy, &mut HashMap::new())?;
     Ok(ans as i64)
 }
 
+pub fn solve(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<i64> {
+    solve_with_policy(lines, UndefinedPolicy::Error)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,8 +290,11 @@ mod tests {
             .filter(|line| !line.as_ref().unwrap().is_empty()) // Skip empty lines
             .collect();
 
-        let result = solve(input.into_iter()).unwrap();
-        assert_eq!(result, 5);
+        // no "svr" device, so check part 1 directly rather than through `solve`
+        let graph = Graph::from_lines(input.into_iter()).unwrap();
+        let policy = UndefinedPolicy::Error;
+        assert_eq!(dfs("you", &graph, policy, &mut HashMap::new()), Ok(5));
+        assert_eq!(bfs(&graph, policy), Ok(5));
     }
 
     #[test]
@@ -143,4 +322,47 @@ mod tests {
         let result = solve(input.into_iter()).unwrap();
         assert_eq!(result, 2);
     }
+
+    #[test]
+    fn test_malformed_line() {
+        let input = vec![Ok(String::from("you: out")), Ok(String::from("aaa out"))];
+        let err = Graph::from_lines(input.into_iter()).unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<GraphError>().unwrap();
+        assert_eq!(*inner, GraphError::MissingColon { line: 2 });
+    }
+
+    #[test]
+    fn test_validate() {
+        let input_str = "
+            you: aaa bbb
+            aaa: out
+            bbb: ccc
+            aaa: bbb
+            ddd: out";
+        let input = input_str.lines().map(|line| Ok(line.trim().to_string()));
+        let graph = Graph::from_lines(input).unwrap();
+        let report = graph.validate(&["you"]);
+        assert_eq!(report.undefined, vec!["ccc".to_string()]);
+        assert_eq!(report.duplicates, vec![("aaa".to_string(), 3, 5)]);
+        assert_eq!(report.unreachable, vec!["ddd".to_string()]);
+
+        assert_eq!(
+            dfs("you", &graph, UndefinedPolicy::Error, &mut HashMap::new()),
+            Err(GraphError::Undefined {
+                node: "ccc".to_string()
+            })
+        );
+        assert_eq!(
+            dfs("you", &graph, UndefinedPolicy::Sink, &mut HashMap::new()),
+            Ok(1)
+        );
+
+        let lines = || input_str.lines().map(|line| Ok(line.trim().to_string()));
+        assert!(solve(lines()).is_err());
+        // "svr" is itself undefined, so as a sink it has no paths
+        assert_eq!(
+            solve_with_policy(lines(), UndefinedPolicy::Sink).unwrap(),
+            0
+        );
+    }
 }
@@ -9,12 +9,50 @@ mod day08;
 mod day09;
 mod day10;
 mod day11;
+#[cfg(test)]
+mod test_util;
 
+use std::env;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 
+fn bad_arg(arg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("bad argument: {}", arg),
+    )
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: &str) -> io::Result<T> {
+    arg.parse().map_err(|_| bad_arg(arg))
+}
+
+/// Usage: `aoc25 [DAY] [DAY OPTIONS...]`
+///   day 1: `aoc25 1 trace [zero]` writes the dial trace as CSV to stdout,
+///   with `zero` only the instructions that passed or stopped on 0.
+///   day 2: `aoc25 2 report [BASE]` lists the invalid IDs of every range with
+///   their repeating block, reading the ranges in BASE (default 10).
+///   day 3: `aoc25 3 [DIGITS [MODE]]` picks DIGITS batteries per bank for part 2,
+///   by MODE: `max` (default), `min`, `min0` (leading zeros allowed), `gap:N`
+///   (largest with picks at most N apart) or `div:N` (largest multiple of N).
+///   day 4: `aoc25 4 frames` writes every removal wave as an ASCII frame to
+///   stdout, ending with the stable grid, then the rolls removed per wave.
+///   day 5: `aoc25 5 (query|batch) [FILE]` answers `fresh`/`spoiled` for each
+///   ID in FILE (default stdin) against the ranges of the day 5 input;
+///   `aoc25 5 json` writes the range-union report as JSON to stdout.
+///   day 6: `aoc25 6 (i64|i128|big)` evaluates the worksheet in that precision.
+///   day 9: `aoc25 9 svg [grid]` writes an SVG of the tiles and the part 2
+///   rectangle to stdout, with `grid` also the compressed grid;
+///   `aoc25 9 stats [W:H]` prints the allowed-region queries, with `W:H` also
+///   the largest red-cornered rectangle of that aspect ratio.
+///   day 11: `aoc25 11 (error|sink)` sets how devices without a definition line
+///   are handled (default: error); `aoc25 11 validate` prints the graph checks.
 fn main() -> io::Result<()> {
-    let day = 11;
+    let args: Vec<String> = env::args().skip(1).collect();
+    let day: u32 = match args.first() {
+        Some(arg) => parse_arg(arg)?,
+        None => 11,
+    };
     let solve = match day {
         1 => day01::solve01,
         2 => day02::solve02,
@@ -32,8 +70,156 @@ fn main() -> io::Result<()> {
     let filename = format!("assets/input{:02}.txt", day);
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
-    let res = solve(reader.lines())?;
-    println!("Day {} solution: {}", day, res);
+    // `None` when the mode has already written its own output
+    let res = match (day, args.get(1)) {
+        (1, Some(mode)) if mode == "trace" => {
+            let zero_only = match args.get(2).map(String::as_str) {
+                None => false,
+                Some("zero") => true,
+                Some(other) => return Err(bad_arg(other)),
+            };
+            let steps = day01::trace(reader.lines(), day01::Dial::default())?;
+            let mut out = BufWriter::new(io::stdout().lock());
+            day01::write_trace_csv(&steps, zero_only, &mut out)?;
+            out.flush()?;
+            None
+        }
+        (2, Some(mode)) if mode == "report" => {
+            let base = match args.get(2) {
+                Some(base) => parse_arg(base)?,
+                None => 10,
+            };
+            let reports = day02::report_in_base(reader.lines(), base)?;
+            for report in &reports {
+                print!("{}", report);
+            }
+            let count: usize = reports.iter().map(|r| r.ids.len()).sum();
+            Some(format!("{} invalid IDs", count))
+        }
+        (3, Some(digits)) => {
+            let mode = match args.get(2).map(String::as_str) {
+                None | Some("max") => day03::Mode::Max,
+                Some("min") => day03::Mode::Min(day03::LeadingZeros::Forbid),
+                Some("min0") => day03::Mode::Min(day03::LeadingZeros::Allow),
+                Some(other) => match other.split_once(':') {
+                    Some(("gap", gap)) => day03::Mode::MaxGap(parse_arg(gap)?),
+                    Some(("div", modulus)) => match parse_arg(modulus)? {
+                        0 => return Err(bad_arg(other)),
+                        modulus => day03::Mode::MaxDivisible(modulus),
+                    },
+                    _ => return Err(bad_arg(other)),
+                },
+            };
+            Some(day03::solve03_with_mode(
+                reader.lines(),
+                parse_arg(digits)?,
+                mode,
+            )?)
+        }
+        (4, Some(mode)) if mode == "frames" => {
+            let erosion = day04::Erosion::from_lines(reader.lines(), &day04::Rule::default())?;
+            let mut out = BufWriter::new(io::stdout().lock());
+            erosion.write_frames(&mut out)?;
+            out.flush()?;
+            drop(out);
+            Some(format!(
+                "{} removed, per wave {:?}",
+                erosion.removed(),
+                erosion.counts()
+            ))
+        }
+        (5, Some(mode)) if mode == "json" => {
+            let inventory = day05::Inventory::from_lines(reader.lines())?;
+            println!("{}", day05::UnionReport::new(&inventory.ranges)?.to_json());
+            None
+        }
+        (5, Some(mode)) => {
+            let mode = match mode.as_str() {
+                "query" => day05::QueryMode::Stream,
+                "batch" => day05::QueryMode::Batch,
+                _ => return Err(bad_arg(mode)),
+            };
+            let set = day05::RangeSet::new(&day05::Inventory::from_lines(reader.lines())?.ranges);
+            let queries: Box<dyn BufRead> = match args.get(2) {
+                Some(path) => Box::new(BufReader::new(File::open(path)?)),
+                None => Box::new(io::stdin().lock()),
+            };
+            let mut out = BufWriter::new(io::stdout().lock());
+            let (fresh, spoiled) = day05::answer_queries(&set, queries.lines(), mode, &mut out)?;
+            out.flush()?;
+            drop(out);
+            Some(format!("{} fresh, {} spoiled", fresh, spoiled))
+        }
+        (6, Some(precision)) => {
+            let precision = match precision.as_str() {
+                "i64" => day06::Precision::I64,
+                "i128" => day06::Precision::I128,
+                "big" => day06::Precision::Big,
+                _ => return Err(bad_arg(precision)),
+            };
+            Some(day06::solve_with_precision(reader.lines(), precision)?)
+        }
+        (9, Some(mode)) if mode == "svg" => {
+            let show_grid = match args.get(2).map(String::as_str) {
+                None => false,
+                Some("grid") => true,
+                Some(other) => return Err(bad_arg(other)),
+            };
+            let svg = day09::svg_part_2(reader.lines(), day09::FillRule::EvenOdd, show_grid)?;
+            print!("{}", svg);
+            None
+        }
+        (9, Some(mode)) if mode == "stats" => {
+            let aspect = match args.get(2) {
+                Some(ratio) => {
+                    let (w, h) = ratio.split_once(':').ok_or_else(|| bad_arg(ratio))?;
+                    Some((parse_arg::<i64>(w)?, parse_arg::<i64>(h)?))
+                }
+                None => None,
+            };
+            let grid = day09::AllowedGrid::from_lines(reader.lines(), day09::FillRule::EvenOdd)?;
+            let show =
+                |r: Option<day09::Rectangle>| r.map_or(String::from("none"), |r| r.to_string());
+            println!("largest rectangle: {}", show(grid.largest_rectangle()));
+            if let Some((w, h)) = aspect {
+                println!(
+                    "largest {}:{} rectangle: {}",
+                    w,
+                    h,
+                    show(grid.largest_with_aspect(w, h))
+                );
+            }
+            println!("valid red pairs: {}", grid.count_valid_pairs());
+            println!("enclosed tiles: {}", grid.enclosed_tiles());
+            Some(
+                grid.largest_red_rectangle()
+                    .map_or(0, |r| r.area)
+                    .to_string(),
+            )
+        }
+        (11, Some(mode)) if mode == "validate" => {
+            let graph = day11::Graph::from_lines(reader.lines())?;
+            let report = graph.validate(&["you", "svr"]);
+            print!("{}", report);
+            Some(String::from(if report.is_clean() {
+                "clean"
+            } else {
+                "issues found"
+            }))
+        }
+        (11, Some(policy)) => {
+            let policy = match policy.as_str() {
+                "error" => day11::UndefinedPolicy::Error,
+                "sink" => day11::UndefinedPolicy::Sink,
+                _ => return Err(bad_arg(policy)),
+            };
+            Some(day11::solve_with_policy(reader.lines(), policy)?.to_string())
+        }
+        _ => Some(solve(reader.lines())?.to_string()),
+    };
+    if let Some(res) = res {
+        println!("Day {} solution: {}", day, res);
+    }
 
     // let sol02 = solve02(reader.lines())?;
     Ok(())